@@ -0,0 +1,298 @@
+use crate::types::{StationId, StationMetadata};
+use crate::uptime::StationResult;
+use std::collections::BTreeMap;
+
+/// Percentiles reported for every group.
+pub const PERCENTILES: [u8; 3] = [5, 50, 95];
+
+/// Uptime figures for the stations sharing one combination of metadata values.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupUptime {
+    /// The value of each grouping key; `None` where the stations do not set
+    /// the key. Empty for the single group formed without keys.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub key: BTreeMap<String, Option<String>>,
+    /// Stations in the group with computed figures.
+    pub stations: usize,
+    /// Stations in the group without reporting time; they do not affect the figures.
+    pub no_data: usize,
+    /// Sum of the stations' available time, wide enough that long reports
+    /// from many stations cannot overflow it.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_sum"))]
+    pub available: u128,
+    /// Sum of the stations' reported time.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_sum"))]
+    pub reported: u128,
+    /// Time-weighted uptime, `100 * available / reported`, so each station
+    /// weighs by its reported time. `None` when no station has data.
+    pub uptime: Option<f64>,
+    /// Lowest and highest station percentage.
+    pub min: Option<u8>,
+    pub max: Option<u8>,
+    /// Nearest-rank percentiles of the station percentages, one per
+    /// `PERCENTILES` entry, keyed `p5`, `p50`, ...
+    pub percentiles: BTreeMap<String, Option<u8>>,
+}
+
+/// Deserialize a time sum from any unsigned integer. `u128` alone would do,
+/// but `#[serde(flatten)]` in `FleetSummary` buffers the fields and only
+/// hands them over as `u64`.
+#[cfg(feature = "serde")]
+fn deserialize_sum<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    struct SumVisitor;
+    impl serde::de::Visitor<'_> for SumVisitor {
+        type Value = u128;
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a non-negative integer")
+        }
+        fn visit_u64<E>(self, v: u64) -> Result<u128, E> {
+            Ok(v.into())
+        }
+        fn visit_u128<E>(self, v: u128) -> Result<u128, E> {
+            Ok(v)
+        }
+    }
+    deserializer.deserialize_any(SumVisitor)
+}
+
+/// Group station results by the metadata values under `keys` (fields such as
+/// `region` or tag names) and summarize each group. With no keys, all
+/// stations form a single group. Groups are sorted by the key values in
+/// `keys` order, with stations missing a key first.
+pub fn aggregate_results(
+    results: &[StationResult],
+    metadata: &BTreeMap<StationId, StationMetadata>,
+    keys: &[String],
+) -> Vec<GroupUptime> {
+    let mut groups: BTreeMap<Vec<Option<String>>, Vec<&StationResult>> = BTreeMap::new();
+    for result in results {
+        let station = metadata.get(&result.station());
+        let key = keys
+            .iter()
+            .map(|k| station.and_then(|m| m.get(k)).map(str::to_string))
+            .collect();
+        groups.entry(key).or_default().push(result);
+    }
+
+    groups
+        .into_iter()
+        .map(|(values, members)| {
+            let computed: Vec<_> = members
+                .iter()
+                .filter_map(|result| match result {
+                    StationResult::Computed(r) => Some(r),
+                    StationResult::NoData(_) => None,
+                })
+                .collect();
+            let available: u128 = computed.iter().map(|r| u128::from(r.available)).sum();
+            let reported: u128 = computed.iter().map(|r| u128::from(r.reported)).sum();
+            let mut percents: Vec<u8> = computed.iter().map(|r| r.percent).collect();
+            percents.sort_unstable();
+            GroupUptime {
+                key: keys.iter().cloned().zip(values).collect(),
+                stations: computed.len(),
+                no_data: members.len() - computed.len(),
+                available,
+                reported,
+                uptime: (reported > 0).then(|| 100.0 * available as f64 / reported as f64),
+                min: percents.first().copied(),
+                max: percents.last().copied(),
+                percentiles: PERCENTILES
+                    .iter()
+                    .map(|&p| (format!("p{p}"), percentile(&percents, p)))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Fleet-wide figures on top of the single all-stations group.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FleetSummary {
+    /// All stations as one group.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub fleet: GroupUptime,
+    /// The target passed to `fleet_summary`.
+    pub target: f64,
+    /// Stations whose exact uptime is below `target`.
+    pub below_target: usize,
+    /// The lowest exact uptimes, ascending, ties broken by station id.
+    pub worst: Vec<RankedStation>,
+}
+
+/// A station and its exact, unfloored uptime percentage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RankedStation {
+    pub station: StationId,
+    pub uptime: f64,
+}
+
+/// Summarize all stations: the fleet group, how many stations are below
+/// `target` percent and the `worst` lowest stations. Like SLA checks, the
+/// comparisons use the exact uptime rather than the floored percentage.
+pub fn fleet_summary(results: &[StationResult], target: f64, worst: usize) -> FleetSummary {
+    let fleet = aggregate_results(results, &BTreeMap::new(), &[])
+        .pop()
+        .unwrap_or_else(|| GroupUptime {
+            key: BTreeMap::new(),
+            stations: 0,
+            no_data: 0,
+            available: 0,
+            reported: 0,
+            uptime: None,
+            min: None,
+            max: None,
+            percentiles: PERCENTILES
+                .iter()
+                .map(|p| (format!("p{p}"), None))
+                .collect(),
+        });
+    let mut uptimes: Vec<RankedStation> = results
+        .iter()
+        .filter_map(|result| match result {
+            StationResult::Computed(r) => Some(RankedStation {
+                station: r.station,
+                uptime: 100.0 * r.available as f64 / r.reported as f64,
+            }),
+            StationResult::NoData(_) => None,
+        })
+        .collect();
+    let below_target = uptimes.iter().filter(|r| r.uptime < target).count();
+    uptimes.sort_by(|a, b| {
+        a.uptime
+            .total_cmp(&b.uptime)
+            .then(a.station.cmp(&b.station))
+    });
+    uptimes.truncate(worst);
+    FleetSummary {
+        fleet,
+        target,
+        below_target,
+        worst: uptimes,
+    }
+}
+
+/// Nearest-rank percentile of sorted `values`: the smallest value with at
+/// least `p` percent of the values at or below it.
+pub fn percentile(sorted: &[u8], p: u8) -> Option<u8> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (usize::from(p) * sorted.len()).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uptime::StationUptime;
+
+    fn computed(station: u32, available: u64, reported: u64) -> StationResult {
+        let percent = (100 * u128::from(available) / u128::from(reported)) as u8;
+        StationResult::Computed(StationUptime {
+            station: StationId(station),
+            percent,
+            raw_percent: percent,
+            available,
+            reported,
+            excluded: 0,
+            excused: 0,
+            by_status: BTreeMap::new(),
+        })
+    }
+
+    #[test]
+    fn mean_is_weighted_by_reported_time() {
+        let mut metadata: BTreeMap<StationId, StationMetadata> = BTreeMap::new();
+        for (station, region) in [(1, "north"), (2, "north"), (3, "south")] {
+            metadata
+                .entry(StationId(station))
+                .or_default()
+                .set("region", region.to_string());
+        }
+        let results = vec![
+            computed(1, 90, 100),
+            computed(2, 300, 300),
+            computed(3, 50, 100),
+            StationResult::NoData(StationId(4)),
+        ];
+        let groups = aggregate_results(&results, &metadata, &["region".to_string()]);
+        let keys: Vec<_> = groups.iter().map(|g| g.key["region"].as_deref()).collect();
+        assert_eq!(keys, vec![None, Some("north"), Some("south")]);
+
+        let none = &groups[0];
+        assert_eq!((none.stations, none.no_data, none.uptime), (0, 1, None));
+        let north = &groups[1];
+        // (90 + 300) / (100 + 300), not the mean of 90% and 100%
+        assert_eq!(north.uptime, Some(97.5));
+        assert_eq!((north.min, north.max), (Some(90), Some(100)));
+        let percentiles: Vec<_> = north.percentiles.values().copied().collect();
+        assert_eq!(percentiles, vec![Some(90), Some(90), Some(100)]);
+
+        let fleet = aggregate_results(&results, &metadata, &[]);
+        assert_eq!(fleet.len(), 1);
+        assert_eq!((fleet[0].stations, fleet[0].no_data), (3, 1));
+    }
+
+    #[test]
+    fn sums_do_not_overflow_on_long_reports() {
+        let results = vec![computed(1, u64::MAX, u64::MAX), computed(2, 0, u64::MAX)];
+        let groups = aggregate_results(&results, &BTreeMap::new(), &[]);
+        assert_eq!(groups[0].available, u128::from(u64::MAX));
+        assert_eq!(groups[0].reported, 2 * u128::from(u64::MAX));
+        assert_eq!(groups[0].uptime, Some(50.0));
+    }
+
+    #[test]
+    fn fleet_summary_ranks_worst_stations() {
+        let results = vec![
+            computed(1, 99, 100),
+            computed(2, 50, 100),
+            computed(3, 96, 100),
+            computed(4, 50, 100),
+            StationResult::NoData(StationId(5)),
+        ];
+        let summary = fleet_summary(&results, 97.0, 3);
+        assert_eq!(summary.fleet.uptime, Some(73.75));
+        assert_eq!(summary.fleet.no_data, 1);
+        assert_eq!(summary.below_target, 3);
+        let worst: Vec<_> = summary
+            .worst
+            .iter()
+            .map(|r| (r.station.0, r.uptime))
+            .collect();
+        assert_eq!(worst, vec![(2, 50.0), (4, 50.0), (3, 96.0)]);
+
+        let empty = fleet_summary(&[], 97.0, 10);
+        assert_eq!((empty.fleet.uptime, empty.below_target), (None, 0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn fleet_summary_serializes_flat() {
+        let summary = fleet_summary(&[computed(1, 50, 100)], 97.0, 1);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["uptime"], 50.0);
+        assert_eq!(json["percentiles"]["p50"], 50);
+        assert_eq!(json["worst"][0]["station"], 1);
+        assert!(json.get("key").is_none());
+        let back: FleetSummary = serde_json::from_value(json).unwrap();
+        assert_eq!(back, summary);
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let values: Vec<u8> = (1..=20).collect();
+        assert_eq!(percentile(&values, 5), Some(1));
+        assert_eq!(percentile(&values, 50), Some(10));
+        assert_eq!(percentile(&values, 95), Some(19));
+        assert_eq!(percentile(&values, 100), Some(20));
+        assert_eq!(percentile(&[], 50), None);
+    }
+}
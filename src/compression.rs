@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How an input is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// From a `.gz` or `.zst` file extension, if any.
+    pub fn from_extension(path: &Path) -> Option<Compression> {
+        match path.extension()?.to_str()? {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// From the magic bytes at the start of the data.
+    pub fn from_magic(head: &[u8]) -> Compression {
+        if head.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wrap `reader` so it yields decompressed data. The compression is taken
+/// from `hint` when given and otherwise sniffed from the first bytes, which
+/// are peeked without being consumed.
+pub fn decompress<'a>(
+    mut reader: impl BufRead + 'a,
+    hint: Option<Compression>,
+) -> io::Result<Box<dyn BufRead + 'a>> {
+    let compression = match hint {
+        Some(compression) => compression,
+        None => Compression::from_magic(reader.fill_buf()?),
+    };
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        // Multi-member so concatenated `.gz` files read to the end
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(
+            reader,
+        )?)),
+    })
+}
+
+/// Open a file for streaming, decompressing it transparently. The extension
+/// decides the compression; files without a known extension are sniffed.
+pub fn open_input(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    let path = path.as_ref();
+    let file = BufReader::new(File::open(path)?);
+    decompress(file, Compression::from_extension(path))
+}
+
+/// Read a whole, possibly compressed, file into a string.
+pub fn read_input_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut content = String::new();
+    open_input(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// `path` without a trailing compression extension, so `reports.csv.gz`
+/// can be recognized as CSV.
+pub fn uncompressed_name(path: &str) -> &str {
+    match Compression::from_extension(Path::new(path)) {
+        Some(_) => path.rsplit_once('.').map_or(path, |(stem, _)| stem),
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const TEXT: &str = "[Stations]\n1 100\n";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_all(reader: Box<dyn BufRead + '_>) -> String {
+        io::read_to_string(reader).unwrap()
+    }
+
+    #[test]
+    fn detects_by_extension_and_magic() {
+        assert_eq!(
+            Compression::from_extension(Path::new("a.txt.gz")),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_extension(Path::new("a.ndjson.zst")),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_extension(Path::new("a.txt")), None);
+        assert_eq!(Compression::from_magic(&gzip(b"x")), Compression::Gzip);
+        assert_eq!(Compression::from_magic(TEXT.as_bytes()), Compression::None);
+        assert_eq!(uncompressed_name("reports.csv.gz"), "reports.csv");
+        assert_eq!(uncompressed_name("reports.csv"), "reports.csv");
+    }
+
+    #[test]
+    fn decompresses_gzip_and_zstd() {
+        let gz = gzip(TEXT.as_bytes());
+        assert_eq!(read_all(decompress(&gz[..], None).unwrap()), TEXT);
+
+        // Concatenated gzip members read as one stream
+        let mut two = gz.clone();
+        two.extend(gzip(b"2 200\n"));
+        assert_eq!(
+            read_all(decompress(&two[..], Some(Compression::Gzip)).unwrap()),
+            format!("{TEXT}2 200\n")
+        );
+
+        let zst = zstd::encode_all(TEXT.as_bytes(), 0).unwrap();
+        assert_eq!(read_all(decompress(&zst[..], None).unwrap()), TEXT);
+
+        assert_eq!(read_all(decompress(TEXT.as_bytes(), None).unwrap()), TEXT);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::parser::{ParseOptions, ParseWarning, ParsedInput, report_from_fields};
+use crate::types::{ChargerId, ChargerReport, Station, StationId, StationMetadata};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+
+#[derive(thiserror::Error, Debug)]
+pub enum CsvError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("missing column '{0}' in CSV header")]
+    MissingColumn(String),
+    #[error("invalid CSV: {0}")]
+    InvalidRecord(String),
+}
+
+/// Column names and delimiter of CSV exports. The defaults match a header
+/// row of `station_id,charger_id` for stations and
+/// `charger_id,start,end,up` for reports, plus an optional `connector_id`.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub station_column: String,
+    pub charger_column: String,
+    /// Optional; reports name no connector when the header lacks it.
+    pub connector_column: String,
+    pub start_column: String,
+    pub end_column: String,
+    /// Holds `true`/`false` or a `ChargerStatus` name.
+    pub status_column: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            station_column: "station_id".into(),
+            charger_column: "charger_id".into(),
+            connector_column: "connector_id".into(),
+            start_column: "start".into(),
+            end_column: "end".into(),
+            status_column: "up".into(),
+        }
+    }
+}
+
+/// Parse a stations CSV with one `station, charger` row per charger.
+/// A station's chargers may span several rows.
+pub fn parse_csv_stations(input: &str, csv: &CsvOptions) -> Result<ParsedInput, CsvError> {
+    let mut reader = reader(input.as_bytes(), csv);
+    let headers = reader.headers()?.clone();
+    let station_col = column(&headers, &csv.station_column)?;
+    let charger_col = column(&headers, &csv.charger_column)?;
+
+    let mut doc = ParsedInput::default();
+    let mut index: BTreeMap<StationId, usize> = BTreeMap::new();
+    let mut seen_chargers: BTreeSet<ChargerId> = BTreeSet::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record_line(&record);
+        let station = StationId(field(&record, station_col, "station id", line)?);
+        let charger = ChargerId(field(&record, charger_col, "charger id", line)?);
+        if !seen_chargers.insert(charger) {
+            return Err(CsvError::InvalidRecord(format!(
+                "charger id {} appears more than once (line {})",
+                charger.0, line
+            )));
+        }
+        let i = *index.entry(station).or_insert_with(|| {
+            doc.station_lines.insert(station, line);
+            doc.stations.push(Station {
+                id: station,
+                chargers: Vec::new(),
+            });
+            doc.stations.len() - 1
+        });
+        doc.stations[i].chargers.push(charger);
+    }
+    if doc.stations.is_empty() {
+        return Err(CsvError::InvalidRecord("no stations found".into()));
+    }
+    Ok(doc)
+}
+
+/// Parse a station metadata CSV with one row per station: the station column
+/// plus any of `name`, `region`, `operator` and `network`; every other column
+/// is a tag. Empty cells are left unset.
+pub fn parse_csv_metadata(input: &str, csv: &CsvOptions) -> Result<ParsedInput, CsvError> {
+    let mut reader = reader(input.as_bytes(), csv);
+    let headers = reader.headers()?.clone();
+    let station_col = column(&headers, &csv.station_column)?;
+    for (col, key) in headers.iter().enumerate() {
+        if col != station_col {
+            StationMetadata::check(key, "")
+                .map_err(|message| CsvError::InvalidRecord(format!("{message} in header")))?;
+        }
+    }
+
+    let mut doc = ParsedInput::default();
+    for record in reader.records() {
+        let record = record?;
+        let line = record_line(&record);
+        let station = StationId(field(&record, station_col, "station id", line)?);
+        if doc.metadata.contains_key(&station) {
+            return Err(CsvError::InvalidRecord(format!(
+                "station id {} appears more than once (line {})",
+                station.0, line
+            )));
+        }
+        let metadata = doc.metadata.entry(station).or_default();
+        for (col, (key, value)) in headers.iter().zip(record.iter()).enumerate() {
+            if col != station_col && !value.is_empty() {
+                StationMetadata::check(key, value).map_err(|message| {
+                    CsvError::InvalidRecord(format!("{message} (line {line})"))
+                })?;
+                metadata.set(key, value.to_string());
+            }
+        }
+    }
+    Ok(doc)
+}
+
+/// Parse a reports CSV against the stations and assignments in `topology`,
+/// with the same unknown-charger check and lenient handling as
+/// `parse_reports_with`.
+pub fn parse_csv_reports_with(
+    input: &str,
+    csv: &CsvOptions,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+) -> Result<ParsedInput, CsvError> {
+    parse_csv_reports_reader(input.as_bytes(), csv, topology, options)
+}
+
+/// Like `parse_csv_reports_with`, streaming records from `input`.
+pub fn parse_csv_reports_reader(
+    input: impl Read,
+    csv: &CsvOptions,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+) -> Result<ParsedInput, CsvError> {
+    let mut reader = reader(input, csv);
+    let headers = reader.headers()?.clone();
+    let columns = [
+        column(&headers, &csv.charger_column)?,
+        column(&headers, &csv.start_column)?,
+        column(&headers, &csv.end_column)?,
+        column(&headers, &csv.status_column)?,
+    ];
+    let connector_col = column(&headers, &csv.connector_column).ok();
+    let known = topology.known_chargers();
+
+    let mut doc = ParsedInput::default();
+    for record in reader.records() {
+        let record = record?;
+        let line = record_line(&record);
+        match parse_report(&record, columns, connector_col, &known) {
+            Ok(report) => doc.reports.push(report),
+            Err(message) if options.lenient => doc.warnings.push(ParseWarning {
+                line,
+                message: format!("{message} (line {line})"),
+            }),
+            Err(message) => {
+                return Err(CsvError::InvalidRecord(format!("{message} (line {line})")));
+            }
+        }
+    }
+    if doc.reports.is_empty() {
+        return Err(CsvError::InvalidRecord(
+            "no charger availability reports found".into(),
+        ));
+    }
+    Ok(doc)
+}
+
+fn reader<R: Read>(input: R, csv: &CsvOptions) -> csv::Reader<R> {
+    csv::ReaderBuilder::new()
+        .delimiter(csv.delimiter)
+        .trim(csv::Trim::All)
+        .from_reader(input)
+}
+
+fn column(headers: &csv::StringRecord, name: &str) -> Result<usize, CsvError> {
+    headers
+        .iter()
+        .position(|h| h == name)
+        .ok_or_else(|| CsvError::MissingColumn(name.to_string()))
+}
+
+fn record_line(record: &csv::StringRecord) -> usize {
+    record.position().map_or(0, |p| p.line() as usize)
+}
+
+fn field<T: std::str::FromStr>(
+    record: &csv::StringRecord,
+    col: usize,
+    what: &str,
+    line: usize,
+) -> Result<T, CsvError> {
+    record
+        .get(col)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| CsvError::InvalidRecord(format!("invalid {what} at line {line}")))
+}
+
+/// One report record; errors are messages without the line number.
+fn parse_report(
+    record: &csv::StringRecord,
+    [charger_col, start_col, end_col, status_col]: [usize; 4],
+    connector_col: Option<usize>,
+    known: &BTreeSet<ChargerId>,
+) -> Result<ChargerReport, String> {
+    let get = |col: usize| record.get(col).unwrap_or("");
+    let report = report_from_fields(
+        get(charger_col),
+        connector_col.map(get).filter(|v| !v.is_empty()),
+        get(start_col),
+        get(end_col),
+        get(status_col),
+    )?;
+    if !known.contains(&report.charger) {
+        return Err(format!(
+            "report references unknown charger id {}",
+            report.charger.0
+        ));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChargerStatus, Interval};
+
+    #[test]
+    fn reads_reports_with_default_columns() {
+        let topology = parse_csv_stations(
+            "station_id,charger_id\n1,100\n1,101\n2,200\n",
+            &CsvOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(topology.stations.len(), 2);
+        assert_eq!(
+            topology.stations[0].chargers,
+            vec![ChargerId(100), ChargerId(101)]
+        );
+
+        let input = "charger_id,start,end,up\n100,0,10,true\n200, 0, 10, faulted\n";
+        let doc = parse_csv_reports_with(
+            input,
+            &CsvOptions::default(),
+            &topology,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(doc.reports.len(), 2);
+        assert_eq!(doc.reports[1].status, ChargerStatus::Faulted);
+    }
+
+    #[test]
+    fn reads_metadata_with_tags() {
+        let input = "station_id,name,region,parking\n1,Main Street,north,covered\n2,Depot,,\n";
+        let doc = parse_csv_metadata(input, &CsvOptions::default()).unwrap();
+        let first = &doc.metadata[&StationId(1)];
+        assert_eq!(first.name.as_deref(), Some("Main Street"));
+        assert_eq!(first.tags["parking"], "covered");
+        let second = &doc.metadata[&StationId(2)];
+        assert_eq!(second.region, None);
+        assert!(second.tags.is_empty());
+
+        let err =
+            parse_csv_metadata("station_id,name\n1,a\n1,b\n", &CsvOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("appears more than once (line 3)"));
+    }
+
+    #[test]
+    fn metadata_must_round_trip_through_text() {
+        let input = "station_id,name,hardware\n1,Main Street,ABB Terra 54\n";
+        let doc = parse_csv_metadata(input, &CsvOptions::default()).unwrap();
+        let mut text = crate::parser::parse_document(
+            "[Stations]\n1 100\n[Charger Availability Reports]\n100 0 10 true\n",
+        )
+        .unwrap();
+        text.metadata = doc.metadata.clone();
+        let written = crate::writer::write_document(&text);
+        let back = crate::parser::parse_document(&written).unwrap();
+        assert_eq!(back.metadata, doc.metadata);
+
+        for (input, message) in [
+            (
+                "station_id,hardware model\n1,ABB\n",
+                "invalid metadata key 'hardware model': keys cannot contain whitespace in header",
+            ),
+            (
+                "station_id,uptime\n1,high\n",
+                "metadata key 'uptime' is reserved for an output column in header",
+            ),
+            (
+                "station_id,bay\n1,Bay #3\n",
+                "metadata value 'Bay #3' for 'bay' cannot contain '#' or line breaks (line 2)",
+            ),
+        ] {
+            let err = parse_csv_metadata(input, &CsvOptions::default()).unwrap_err();
+            assert_eq!(err.to_string(), format!("invalid CSV: {message}"));
+        }
+    }
+
+    #[test]
+    fn custom_columns_and_delimiter() {
+        let csv = CsvOptions {
+            delimiter: b';',
+            charger_column: "evse".into(),
+            start_column: "from_ns".into(),
+            end_column: "to_ns".into(),
+            status_column: "state".into(),
+            ..CsvOptions::default()
+        };
+        let topology = crate::parser::parse_topology("[Stations]\n1 100\n").unwrap();
+        let input = "state;evse;to_ns;from_ns\nup;100;10;0\n";
+        let doc = parse_csv_reports_with(input, &csv, &topology, &ParseOptions::default()).unwrap();
+        assert_eq!(doc.reports[0].interval, Interval { start: 0, end: 10 });
+
+        let err = parse_csv_reports_with(
+            input,
+            &CsvOptions::default(),
+            &topology,
+            &ParseOptions::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, CsvError::MissingColumn(c) if c == "charger_id"));
+    }
+
+    #[test]
+    fn bad_records_fail_or_warn() {
+        let topology = crate::parser::parse_topology("[Stations]\n1 100\n").unwrap();
+        let input = "charger_id,start,end,up\n100,0,10,true\n999,0,10,true\n100,10,5,false\n";
+        let err = parse_csv_reports_with(
+            input,
+            &CsvOptions::default(),
+            &topology,
+            &ParseOptions::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown charger id 999 (line 3)"));
+
+        let doc = parse_csv_reports_with(
+            input,
+            &CsvOptions::default(),
+            &topology,
+            &ParseOptions { lenient: true },
+        )
+        .unwrap();
+        assert_eq!(doc.reports.len(), 1);
+        let lines: Vec<usize> = doc.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![3, 4]);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::interval::merge_intervals;
+use crate::parser::{ParseOptions, ParseWarning, ParsedInput};
+use crate::types::{ChargerId, ChargerReport, ChargerStatus, Interval};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufRead;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HeartbeatError {
+    #[error("invalid heartbeat: {0}")]
+    InvalidRecord(String),
+    #[error("failed to read input: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// How often chargers are expected to send a heartbeat, in the same unit as
+/// the heartbeat timestamps.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatOptions {
+    /// Expected time between two heartbeats.
+    pub period: u64,
+    /// Extra time a heartbeat may be late before the charger counts as down.
+    pub grace: u64,
+}
+
+/// Turn one charger's heartbeat timestamps into reports. Each heartbeat
+/// covers `[t, t + period + grace)`; covered time is reported up and the gaps
+/// between covered stretches are reported down.
+pub fn heartbeat_reports(
+    charger: ChargerId,
+    timestamps: &[u64],
+    options: &HeartbeatOptions,
+) -> Vec<ChargerReport> {
+    let reach = options.period.saturating_add(options.grace);
+    let mut covered: Vec<Interval> = timestamps
+        .iter()
+        .map(|&t| Interval {
+            start: t,
+            end: t.saturating_add(reach),
+        })
+        .collect();
+    let covered = merge_intervals(&mut covered);
+
+    let mut reports = Vec::with_capacity(covered.len() * 2);
+    for (i, &interval) in covered.iter().enumerate() {
+        if i > 0 {
+            reports.push(ChargerReport {
+                charger,
+                connector: None,
+                interval: Interval {
+                    start: covered[i - 1].end,
+                    end: interval.start,
+                },
+                status: ChargerStatus::Down,
+            });
+        }
+        reports.push(ChargerReport {
+            charger,
+            connector: None,
+            interval,
+            status: ChargerStatus::Up,
+        });
+    }
+    reports
+}
+
+/// Import a heartbeat log with one `<charger id> <timestamp>` pair per line.
+/// Blank lines and lines starting with `#` are ignored, and lines need not be
+/// sorted. Chargers must be declared in `topology`; invalid lines fail the
+/// import or, with `options.lenient`, become warnings.
+pub fn import_heartbeats(
+    reader: impl BufRead,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+    heartbeat: &HeartbeatOptions,
+) -> Result<ParsedInput, HeartbeatError> {
+    if heartbeat.period == 0 {
+        return Err(HeartbeatError::InvalidRecord(
+            "heartbeat period must be positive".into(),
+        ));
+    }
+    let known = topology.known_chargers();
+
+    let mut doc = ParsedInput::default();
+    let mut beats: BTreeMap<ChargerId, Vec<u64>> = BTreeMap::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_heartbeat(line, &known) {
+            Ok((charger, at)) => beats.entry(charger).or_default().push(at),
+            Err(message) => {
+                let message = format!("line {line_no}: {message}");
+                if !options.lenient {
+                    return Err(HeartbeatError::InvalidRecord(message));
+                }
+                doc.warnings.push(ParseWarning {
+                    line: line_no,
+                    message,
+                });
+            }
+        }
+    }
+
+    for (charger, timestamps) in &beats {
+        doc.reports
+            .extend(heartbeat_reports(*charger, timestamps, heartbeat));
+    }
+    if doc.reports.is_empty() {
+        return Err(HeartbeatError::InvalidRecord("no heartbeats found".into()));
+    }
+    Ok(doc)
+}
+
+fn parse_heartbeat(line: &str, known: &BTreeSet<ChargerId>) -> Result<(ChargerId, u64), String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let [charger, at] = tokens[..] else {
+        return Err(format!("expected '<charger id> <timestamp>', got '{line}'"));
+    };
+    let charger = charger
+        .parse()
+        .map(ChargerId)
+        .map_err(|_| format!("invalid charger id '{charger}'"))?;
+    if !known.contains(&charger) {
+        return Err(format!("unknown charger id {}", charger.0));
+    }
+    let at = at
+        .parse()
+        .map_err(|_| format!("invalid timestamp '{at}'"))?;
+    Ok((charger, at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_topology;
+
+    const OPTIONS: HeartbeatOptions = HeartbeatOptions {
+        period: 10,
+        grace: 2,
+    };
+
+    #[test]
+    fn missing_heartbeats_become_down_gaps() {
+        let reports = heartbeat_reports(ChargerId(7), &[30, 0, 10, 20, 60], &OPTIONS);
+        let spans: Vec<_> = reports
+            .iter()
+            .map(|r| (r.interval.start, r.interval.end, r.status))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0, 42, ChargerStatus::Up),
+                (42, 60, ChargerStatus::Down),
+                (60, 72, ChargerStatus::Up),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_checks_chargers_and_lenient_skips() {
+        let topology = parse_topology("[Stations]\n1 100\n").unwrap();
+        let log = "# charger timestamp\n100 0\n100 10\n999 5\n";
+        let err = import_heartbeats(
+            log.as_bytes(),
+            &topology,
+            &ParseOptions::default(),
+            &OPTIONS,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("line 4: unknown charger id 999"));
+
+        let lenient = ParseOptions { lenient: true };
+        let doc = import_heartbeats(log.as_bytes(), &topology, &lenient, &OPTIONS).unwrap();
+        assert_eq!(doc.warnings.len(), 1);
+        assert_eq!(doc.reports.len(), 1);
+        assert_eq!(doc.reports[0].interval, Interval { start: 0, end: 22 });
+    }
+}
@@ -1,6 +1,7 @@
 use crate::types::Interval;
 
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
 
@@ -37,6 +38,51 @@ mod tests {
         let merged = merge_intervals(&mut v);
         assert_eq!(merged, vec![Interval { start: 0, end: 1 }]);
     }
+
+    #[test]
+    fn subtract_splits_and_trims() {
+        let base = vec![Interval { start: 0, end: 100 }];
+        let remove = vec![
+            Interval { start: 10, end: 20 },
+            Interval {
+                start: 90,
+                end: 150,
+            },
+        ];
+        let rest = subtract_intervals(&base, &remove);
+        assert_eq!(
+            rest,
+            vec![
+                Interval { start: 0, end: 10 },
+                Interval { start: 20, end: 90 }
+            ]
+        );
+        assert_eq!(total_duration(&rest), 80);
+    }
+
+    #[test]
+    fn subtract_nothing_or_everything() {
+        let base = vec![Interval { start: 5, end: 10 }];
+        assert_eq!(subtract_intervals(&base, &[]), base);
+        assert!(subtract_intervals(&base, &[Interval { start: 0, end: 10 }]).is_empty());
+    }
+
+    #[test]
+    fn intersect_keeps_common_parts() {
+        let a = vec![
+            Interval { start: 0, end: 10 },
+            Interval { start: 20, end: 30 },
+        ];
+        let b = vec![Interval { start: 5, end: 25 }];
+        assert_eq!(
+            intersect_intervals(&a, &b),
+            vec![
+                Interval { start: 5, end: 10 },
+                Interval { start: 20, end: 25 }
+            ]
+        );
+        assert!(intersect_intervals(&a, &[]).is_empty());
+    }
 }
 
 /// Merge a list of half-open time intervals [start, end) into a set of
@@ -68,3 +114,45 @@ pub fn merge_intervals(intervals: &mut Vec<Interval>) -> Vec<Interval> {
 
     merged
 }
+
+/// Remove every instant covered by `remove` from `base`.
+/// Both inputs must be sorted and disjoint, as returned by `merge_intervals`.
+pub fn subtract_intervals(base: &[Interval], remove: &[Interval]) -> Vec<Interval> {
+    let mut result: Vec<Interval> = Vec::with_capacity(base.len());
+    let mut cut = remove.iter().peekable();
+    for iv in base {
+        let mut start = iv.start;
+        // Skip removals that end before this interval begins
+        while cut.next_if(|r| r.end <= start).is_some() {}
+        for r in cut.clone() {
+            if r.start >= iv.end {
+                break;
+            }
+            if r.start > start {
+                result.push(Interval {
+                    start,
+                    end: r.start,
+                });
+            }
+            start = start.max(r.end);
+            if start >= iv.end {
+                break;
+            }
+        }
+        if start < iv.end {
+            result.push(Interval { start, end: iv.end });
+        }
+    }
+    result
+}
+
+/// Total time covered by a set of disjoint intervals.
+pub fn total_duration(intervals: &[Interval]) -> u64 {
+    intervals.iter().map(|iv| iv.end - iv.start).sum()
+}
+
+/// The parts of `a` also covered by `b`. Both inputs must be sorted and
+/// disjoint, as returned by `merge_intervals`.
+pub fn intersect_intervals(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+    subtract_intervals(a, &subtract_intervals(a, b))
+}
@@ -0,0 +1,229 @@
+use crate::parser::{ParseOptions, ParseWarning, ParsedInput};
+use crate::types::{ChargerId, ChargerReport, Station};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::io::{BufRead, Read};
+
+#[derive(thiserror::Error, Debug)]
+pub enum JsonError {
+    #[error("invalid JSON: {0}")]
+    Syntax(#[from] serde_json::Error),
+    #[error("invalid {0}")]
+    InvalidRecord(String),
+    #[error("failed to read input: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Top level of a JSON document; records are decoded one at a time so errors
+/// can name the record.
+#[derive(Deserialize)]
+struct Document {
+    stations: Vec<serde_json::Value>,
+    reports: Vec<serde_json::Value>,
+}
+
+/// Parse a JSON document of the form
+/// `{"stations": [{"id": 1, "chargers": [100]}], "reports": [{"charger": 100, "start": 0, "end": 10, "status": "up"}]}`.
+///
+/// `status` is a status name or a `true`/`false` flag. The same rules as the
+/// text format apply: ids are unique, reports reference known chargers and
+/// `end > start`. Records are numbered from 1 within their array.
+pub fn parse_json_document_with(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<ParsedInput, JsonError> {
+    parse_json_document_reader(input.as_bytes(), options)
+}
+
+/// Like `parse_json_document_with`, reading the document from `reader`.
+pub fn parse_json_document_reader(
+    reader: impl Read,
+    options: &ParseOptions,
+) -> Result<ParsedInput, JsonError> {
+    let document: Document = serde_json::from_reader(std::io::BufReader::new(reader))?;
+
+    let mut doc = ParsedInput::default();
+    let mut station_ids = BTreeSet::new();
+    let mut known: BTreeSet<ChargerId> = BTreeSet::new();
+    for (i, value) in document.stations.into_iter().enumerate() {
+        let record = i + 1;
+        let station: Station = serde_json::from_value(value)
+            .map_err(|e| JsonError::InvalidRecord(format!("station record {record}: {e}")))?;
+        if !station_ids.insert(station.id) {
+            return Err(JsonError::InvalidRecord(format!(
+                "station record {record}: duplicate station id {}",
+                station.id.0
+            )));
+        }
+        if station.chargers.is_empty() {
+            return Err(JsonError::InvalidRecord(format!(
+                "station record {record}: station {} has no chargers",
+                station.id.0
+            )));
+        }
+        for charger in &station.chargers {
+            if !known.insert(*charger) {
+                return Err(JsonError::InvalidRecord(format!(
+                    "station record {record}: charger id {} appears more than once",
+                    charger.0
+                )));
+            }
+        }
+        doc.station_lines.insert(station.id, record);
+        doc.stations.push(station);
+    }
+    if doc.stations.is_empty() {
+        return Err(JsonError::InvalidRecord(
+            "document: no stations found".into(),
+        ));
+    }
+
+    for (i, value) in document.reports.into_iter().enumerate() {
+        let record = i + 1;
+        let report = serde_json::from_value(value)
+            .map_err(|e| e.to_string())
+            .and_then(|report| check_report(report, &known));
+        push_report(&mut doc, report, record, options)?;
+    }
+    finish(doc)
+}
+
+/// Parse newline-delimited JSON with one report object per line, checked
+/// against the stations and assignments in `topology`. Blank lines are
+/// skipped; records are numbered by line.
+pub fn parse_ndjson_reports_with(
+    input: &str,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+) -> Result<ParsedInput, JsonError> {
+    parse_ndjson_reports_reader(input.as_bytes(), topology, options)
+}
+
+/// Like `parse_ndjson_reports_with`, streaming lines from `reader`.
+pub fn parse_ndjson_reports_reader(
+    reader: impl BufRead,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+) -> Result<ParsedInput, JsonError> {
+    let known = topology.known_chargers();
+
+    let mut doc = ParsedInput::default();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let report = serde_json::from_str(&line)
+            .map_err(|e| e.to_string())
+            .and_then(|report| check_report(report, &known));
+        push_report(&mut doc, report, i + 1, options)?;
+    }
+    finish(doc)
+}
+
+/// Apply the text format's report rules; errors are messages without the record.
+fn check_report(
+    report: ChargerReport,
+    known: &BTreeSet<ChargerId>,
+) -> Result<ChargerReport, String> {
+    if !known.contains(&report.charger) {
+        return Err(format!(
+            "report references unknown charger id {}",
+            report.charger.0
+        ));
+    }
+    if report.interval.end <= report.interval.start {
+        return Err("end must be > start".into());
+    }
+    Ok(report)
+}
+
+/// Keep a valid report, or fail or warn on an invalid one depending on `options`.
+fn push_report(
+    doc: &mut ParsedInput,
+    report: Result<ChargerReport, String>,
+    record: usize,
+    options: &ParseOptions,
+) -> Result<(), JsonError> {
+    match report {
+        Ok(report) => doc.reports.push(report),
+        Err(message) => {
+            let message = format!("report record {record}: {message}");
+            if !options.lenient {
+                return Err(JsonError::InvalidRecord(message));
+            }
+            doc.warnings.push(ParseWarning {
+                line: record,
+                message,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn finish(doc: ParsedInput) -> Result<ParsedInput, JsonError> {
+    if doc.reports.is_empty() {
+        return Err(JsonError::InvalidRecord(
+            "document: no charger availability reports found".into(),
+        ));
+    }
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChargerStatus, Interval, StationId};
+
+    #[test]
+    fn reads_json_document() {
+        let input = r#"{
+            "stations": [{"id": 1, "chargers": [100, 101]}],
+            "reports": [
+                {"charger": 100, "start": 0, "end": 10, "status": true},
+                {"charger": 101, "start": 0, "end": 10, "status": "faulted"}
+            ]
+        }"#;
+        let doc = parse_json_document_with(input, &ParseOptions::default()).unwrap();
+        assert_eq!(doc.stations[0].id, StationId(1));
+        assert_eq!(doc.reports[0].status, ChargerStatus::Up);
+        assert_eq!(doc.reports[1].interval, Interval { start: 0, end: 10 });
+    }
+
+    #[test]
+    fn errors_name_the_record() {
+        let input = r#"{"stations": [{"id": 1, "chargers": [100]}, {"id": "x", "chargers": []}], "reports": []}"#;
+        let err = parse_json_document_with(input, &ParseOptions::default()).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid station record 2:"),
+            "{err}"
+        );
+
+        let input = r#"{"stations": [{"id": 1, "chargers": [100]}], "reports": [
+            {"charger": 100, "start": 0, "end": 10, "status": "up"},
+            {"charger": 100, "start": 0, "end": 10, "status": "broken"}]}"#;
+        let err = parse_json_document_with(input, &ParseOptions::default()).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid report record 2:"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn reads_ndjson_reports() {
+        let topology = crate::parser::parse_topology("[Stations]\n1 100\n").unwrap();
+        let input = "{\"charger\": 100, \"start\": 0, \"end\": 10, \"status\": \"up\"}\n\n{\"charger\": 999, \"start\": 0, \"end\": 10, \"status\": \"up\"}\nnot json\n";
+        let err =
+            parse_ndjson_reports_with(input, &topology, &ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid report record 3: report references unknown charger id 999"
+        );
+
+        let doc =
+            parse_ndjson_reports_with(input, &topology, &ParseOptions { lenient: true }).unwrap();
+        assert_eq!(doc.reports.len(), 1);
+        let records: Vec<usize> = doc.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(records, vec![3, 4]);
+    }
+}
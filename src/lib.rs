@@ -1,6 +1,23 @@
+pub mod aggregate;
+pub mod compression;
+pub mod csv_reader;
+pub mod heartbeat;
 pub mod interval;
+#[cfg(feature = "serde")]
+pub mod json_reader;
+pub mod merge;
+#[cfg(feature = "serde")]
+pub mod ocpp;
 pub mod parser;
+pub mod policy;
+pub mod quality;
+pub mod sla;
 pub mod types;
 pub mod uptime;
+pub mod writer;
 
-pub use types::{ChargerId, ChargerReport, Interval, Station, StationId};
+pub use types::{
+    ChargerAssignment, ChargerId, ChargerReport, ChargerStatus, ChargerToken, ConnectorId,
+    Interval, MaintenanceWindow, OutageCategory, OutageWindow, Station, StationId, StationMetadata,
+    TimeUnit, WindowTarget,
+};
@@ -1,52 +1,1219 @@
-use charger_uptime::{parser::parse_input, uptime::compute_station_uptime};
-use clap::Parser;
-use std::{fs, process::ExitCode};
+use anyhow::Context;
+use charger_uptime::{
+    ChargerId, ChargerToken, ConnectorId, StationId, StationMetadata,
+    aggregate::{FleetSummary, GroupUptime, PERCENTILES, aggregate_results, fleet_summary},
+    compression::{open_input, read_input_to_string, uncompressed_name},
+    csv_reader::{CsvOptions, parse_csv_metadata, parse_csv_reports_reader, parse_csv_stations},
+    heartbeat::{HeartbeatOptions, import_heartbeats},
+    json_reader::{parse_json_document_reader, parse_ndjson_reports_reader},
+    merge::merge_documents,
+    ocpp::{OcppOptions, import_status_notifications},
+    parser::{
+        ParseOptions, ParsedInput, parse_document_reader, parse_reports_reader, parse_topology,
+    },
+    policy::{ExcusePolicy, StatusPolicy},
+    quality::{DEFAULT_MAX_GAP, QualityConfig, ReportOverlap, assess_quality},
+    sla::{SlaBreach, SlaPolicy, parse_sla_config, parse_target},
+    uptime::{
+        Rollup, StationResult, StationUptime, UnitLevel, UptimeConfig, UptimeError,
+        compute_station_outages, compute_station_results, compute_unit_results,
+    },
+    writer::{
+        write_csv_reports, write_csv_stations, write_document, write_json_document,
+        write_ndjson_reports,
+    },
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 /// CLI for computing station uptimes from an input file
 #[derive(Parser, Debug)]
 #[command(
     name = "charger-uptime",
     version,
-    about = "Compute station uptime from availability reports"
+    about = "Compute station uptime from availability reports",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
 struct Cli {
-    /// Path to the input file
-    input_path: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Arguments for the default `compute` command
+    #[command(flatten)]
+    compute: ComputeArgs,
+
+    /// Format of the input file
+    #[arg(long, global = true, value_enum, default_value_t = InputFormat::Text)]
+    input_format: InputFormat,
+
+    /// Format of the output
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Challenge compatibility: print ERROR on failure and always exit 0
+    #[arg(long, global = true)]
+    compat: bool,
+
+    /// End of the last status in an OCPP log (default: the log's latest timestamp)
+    #[arg(long, global = true, value_name = "TIMESTAMP")]
+    ocpp_until: Option<u64>,
+
+    /// Expected time between heartbeats, in the log's time unit
+    #[arg(long, global = true, value_name = "DURATION")]
+    heartbeat_period: Option<u64>,
+
+    /// How late a heartbeat may arrive before the charger counts as down
+    #[arg(long, global = true, value_name = "DURATION", default_value_t = 0)]
+    heartbeat_grace: u64,
+
+    #[command(flatten)]
+    csv: CsvArgs,
+}
+
+/// Column names and delimiter for CSV reports and CSV station files.
+#[derive(Args, Debug)]
+struct CsvArgs {
+    /// Field delimiter of CSV files
+    #[arg(long, global = true, value_name = "CHAR", default_value_t = ',')]
+    csv_delimiter: char,
+
+    /// CSV column holding the station id
+    #[arg(long, global = true, value_name = "NAME", default_value = "station_id")]
+    csv_station_column: String,
+
+    /// CSV column holding the charger id
+    #[arg(long, global = true, value_name = "NAME", default_value = "charger_id")]
+    csv_charger_column: String,
+
+    /// CSV column holding the connector id, if the reports have one
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        default_value = "connector_id"
+    )]
+    csv_connector_column: String,
+
+    /// CSV column holding the report start time
+    #[arg(long, global = true, value_name = "NAME", default_value = "start")]
+    csv_start_column: String,
+
+    /// CSV column holding the report end time
+    #[arg(long, global = true, value_name = "NAME", default_value = "end")]
+    csv_end_column: String,
+
+    /// CSV column holding `true`/`false` or a status name
+    #[arg(long, global = true, value_name = "NAME", default_value = "up")]
+    csv_status_column: String,
+}
+
+impl CsvArgs {
+    fn options(&self) -> anyhow::Result<CsvOptions> {
+        let delimiter = u8::try_from(self.csv_delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .context("--csv-delimiter must be a single ASCII character")?;
+        Ok(CsvOptions {
+            delimiter,
+            station_column: self.csv_station_column.clone(),
+            charger_column: self.csv_charger_column.clone(),
+            connector_column: self.csv_connector_column.clone(),
+            start_column: self.csv_start_column.clone(),
+            end_column: self.csv_end_column.clone(),
+            status_column: self.csv_status_column.clone(),
+        })
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compute station uptime (the default when no subcommand is given)
+    Compute(ComputeArgs),
+    /// Parse the input and print a data-quality report, without computing uptime
+    Validate(ValidateArgs),
+    /// List the periods each station was counted as down
+    Outages(OutagesArgs),
+    /// Summarize what the input contains and the fleet's uptime
+    Stats(StatsArgs),
+    /// Rewrite the input in another input format
+    Convert(ConvertArgs),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    /// The bracketed challenge format
+    Text,
+    /// Reports as CSV with a header row; stations come from `--stations`
+    Csv,
+    /// A JSON document with `stations` and `reports` arrays
+    Json,
+    /// One JSON report object per line; stations come from `--stations`
+    Ndjson,
+    /// NDJSON log of OCPP StatusNotification messages; stations come from `--stations`
+    Ocpp,
+    /// `<charger id> <timestamp>` heartbeat lines; stations come from `--stations`
+    Heartbeat,
+}
+
+impl std::fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.to_possible_value().expect("no variant is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+    /// A header row plus one row per line of text output (compute and outages)
+    Csv,
+}
+
+#[derive(Args, Debug)]
+struct InputArgs {
+    /// Input files or glob patterns; all of them are merged into one input
+    #[arg(required = true, value_name = "INPUT")]
+    input_paths: Vec<String>,
+
+    /// Read stations from this topology file (text, or CSV if it ends in
+    /// `.csv`); the inputs then only need reports
+    #[arg(long, value_name = "PATH")]
+    stations: Option<String>,
+
+    /// Read station metadata from this CSV file: a station id column plus
+    /// `name`, `region`, `operator`, `network` and tag columns
+    #[arg(long, value_name = "PATH")]
+    metadata: Option<String>,
+
+    /// Skip malformed report lines with a warning instead of failing
+    #[arg(long)]
+    lenient: bool,
+}
+
+impl InputArgs {
+    /// The input paths with glob patterns expanded, in the order given.
+    fn paths(&self) -> anyhow::Result<Vec<String>> {
+        let mut paths = Vec::new();
+        for arg in &self.input_paths {
+            if !arg.contains(['*', '?', '[']) {
+                paths.push(arg.clone());
+                continue;
+            }
+            let before = paths.len();
+            for entry in glob::glob(arg).with_context(|| format!("invalid glob '{arg}'"))? {
+                paths.push(entry?.to_string_lossy().into_owned());
+            }
+            if paths.len() == before {
+                anyhow::bail!("no input files match '{arg}'");
+            }
+        }
+        Ok(paths)
+    }
+}
+
+#[derive(Args, Debug)]
+struct PolicyArgs {
+    /// Reclassify a status as available, unavailable or excluded
+    /// (e.g. `--classify maintenance=unavailable`); may be repeated
+    #[arg(long = "classify", value_name = "STATUS=CLASS")]
+    classify: Vec<String>,
+
+    /// Comma-separated outage categories to excuse, or `none`
+    /// (default: grid_outage,vandalism,force_majeure)
+    #[arg(long, value_name = "CATEGORIES")]
+    excuse: Option<String>,
+
+    /// A charger is up while any or all of its reporting connectors are up
+    #[arg(long, value_enum, default_value_t = RollupArg::Any)]
+    connector_rollup: RollupArg,
+
+    /// A station is up while any or all of its reporting chargers are up
+    #[arg(long, value_enum, default_value_t = RollupArg::Any)]
+    charger_rollup: RollupArg,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum RollupArg {
+    Any,
+    All,
+}
+
+impl From<RollupArg> for Rollup {
+    fn from(arg: RollupArg) -> Self {
+        match arg {
+            RollupArg::Any => Rollup::Any,
+            RollupArg::All => Rollup::All,
+        }
+    }
+}
+
+/// What `compute` prints one line per.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Station,
+    Charger,
+    Connector,
+}
+
+#[derive(Args, Debug)]
+struct ComputeArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    policy: PolicyArgs,
+
+    /// Summarize stations per combination of these comma-separated metadata
+    /// keys (e.g. `region,operator` or a tag name) instead of listing them
+    #[arg(long, value_name = "KEYS")]
+    group_by: Option<String>,
+
+    /// Compute uptime per station, per charger or per connector
+    #[arg(long, value_enum, default_value_t = Level::Station)]
+    level: Level,
+
+    /// Append raw uptime, time totals and a per-status breakdown to each line
+    #[arg(long)]
+    breakdown: bool,
+
+    /// Print `N/A` (JSON: null) for stations without reports instead of failing;
+    /// such stations are not checked against `--sla` targets
+    #[arg(long)]
+    allow_missing: bool,
+
+    /// Flag stations whose uptime is below this percentage
+    #[arg(long, value_name = "PERCENT")]
+    sla: Option<String>,
+
+    /// SLA config file with `default <percent>` and `<station_id> <percent>` lines;
+    /// `--sla` overrides its default
+    #[arg(long, value_name = "PATH")]
+    sla_config: Option<String>,
 }
 
+#[derive(Args, Debug)]
+struct ValidateArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Flag gaps between a charger's reports longer than this (default: one hour in ns)
+    #[arg(long, value_name = "DURATION", default_value_t = DEFAULT_MAX_GAP)]
+    max_gap: u64,
+
+    /// Flag reports ending after this timestamp (default: the current time in ns)
+    #[arg(long, value_name = "TIMESTAMP")]
+    now: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+struct OutagesArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    policy: PolicyArgs,
+}
+
+#[derive(Args, Debug)]
+struct StatsArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    policy: PolicyArgs,
+
+    /// Count the stations whose uptime is below this percentage
+    #[arg(long, value_name = "PERCENT", default_value = "97")]
+    below: String,
+
+    /// List this many stations with the lowest uptime
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    worst: usize,
+}
+
+#[derive(Args, Debug)]
+struct ConvertArgs {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Format to convert to
+    #[arg(long, value_enum, default_value_t = InputFormat::Text)]
+    to: InputFormat,
+
+    /// Write to this file instead of stdout
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Write the stations here when the target format only holds reports
+    /// (csv, ndjson); CSV if the path ends in `.csv`, the text format otherwise
+    #[arg(long, value_name = "PATH")]
+    stations_output: Option<String>,
+}
+
+/// How a command finished, mapped to an exit code by `main`.
+enum Outcome {
+    Ok,
+    InvalidInput,
+    SlaBreach,
+}
+
+/// Exit code for unreadable or invalid input, unless `--compat` is set.
+const EXIT_INPUT_ERROR: u8 = 1;
+/// Exit code when any station is below its SLA target, unless `--compat` is set.
+const EXIT_SLA_BREACH: u8 = 3;
+
 fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    let input = match fs::read_to_string(&cli.input_path) {
-        Ok(c) => c,
+    let result = match &cli.command {
+        Some(Command::Compute(args)) => compute(&cli, args),
+        Some(Command::Validate(args)) => validate(&cli, args),
+        Some(Command::Outages(args)) => outages(&cli, args),
+        Some(Command::Stats(args)) => stats(&cli, args),
+        Some(Command::Convert(args)) => convert(&cli, args),
+        None => compute(&cli, &cli.compute),
+    };
+
+    match result {
+        Ok(Outcome::Ok) => ExitCode::SUCCESS,
+        Ok(_) if cli.compat => ExitCode::SUCCESS,
+        Ok(Outcome::InvalidInput) => ExitCode::from(EXIT_INPUT_ERROR),
+        Ok(Outcome::SlaBreach) => ExitCode::from(EXIT_SLA_BREACH),
         Err(e) => {
-            eprintln!("failed to read input: {e}");
-            println!("ERROR");
-            return ExitCode::SUCCESS;
+            eprintln!("{e:#}");
+            if cli.compat {
+                println!("ERROR");
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(EXIT_INPUT_ERROR)
+            }
         }
+    }
+}
+
+fn load_input(cli: &Cli, input: &InputArgs) -> anyhow::Result<ParsedInput> {
+    let options = ParseOptions {
+        lenient: input.lenient,
+    };
+    let csv = cli.csv.options()?;
+    let topology = match &input.stations {
+        Some(path) => {
+            let content =
+                read_input_to_string(path).with_context(|| format!("failed to read {path}"))?;
+            let topology = if uncompressed_name(path).ends_with(".csv") {
+                parse_csv_stations(&content, &csv).map_err(anyhow::Error::from)
+            } else {
+                parse_topology(&content).map_err(anyhow::Error::from)
+            };
+            Some((
+                path,
+                topology.with_context(|| format!("parse error in {path}"))?,
+            ))
+        }
+        None => None,
     };
 
-    let (stations, reports) = match parse_input(&input) {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("parse error: {e}");
-            println!("ERROR");
-            return ExitCode::SUCCESS;
+    let mut docs = Vec::new();
+    for path in input.paths()? {
+        // Streamed straight from the (possibly compressed) file into the parser
+        let reader = open_input(&path).with_context(|| format!("failed to read {path}"))?;
+        let doc = match (cli.input_format, &topology) {
+            (InputFormat::Text, None) => {
+                parse_document_reader(reader, &options).map_err(anyhow::Error::from)
+            }
+            (InputFormat::Text, Some((_, topology))) => {
+                parse_reports_reader(reader, topology, &options).map_err(anyhow::Error::from)
+            }
+            (InputFormat::Csv, Some((_, topology))) => {
+                parse_csv_reports_reader(reader, &csv, topology, &options)
+                    .map_err(anyhow::Error::from)
+            }
+            (InputFormat::Json, _) => {
+                parse_json_document_reader(reader, &options).map_err(anyhow::Error::from)
+            }
+            (InputFormat::Ndjson, Some((_, topology))) => {
+                parse_ndjson_reports_reader(reader, topology, &options).map_err(anyhow::Error::from)
+            }
+            (InputFormat::Ocpp, Some((_, topology))) => {
+                let ocpp = OcppOptions {
+                    until: cli.ocpp_until,
+                };
+                import_status_notifications(reader, topology, &options, &ocpp)
+                    .map_err(anyhow::Error::from)
+            }
+            (InputFormat::Heartbeat, Some((_, topology))) => {
+                let Some(period) = cli.heartbeat_period else {
+                    anyhow::bail!("--input-format heartbeat needs --heartbeat-period");
+                };
+                let heartbeat = HeartbeatOptions {
+                    period,
+                    grace: cli.heartbeat_grace,
+                };
+                import_heartbeats(reader, topology, &options, &heartbeat)
+                    .map_err(anyhow::Error::from)
+            }
+            (
+                InputFormat::Csv | InputFormat::Ndjson | InputFormat::Ocpp | InputFormat::Heartbeat,
+                None,
+            ) => {
+                anyhow::bail!(
+                    "--input-format {} needs a --stations file",
+                    cli.input_format
+                )
+            }
+        }
+        .with_context(|| format!("parse error in {path}"))?;
+        for w in &doc.warnings {
+            eprintln!("warning: {path}: {}", w.message);
+        }
+        docs.push((path, doc));
+    }
+    if let Some((path, topology)) = topology {
+        docs.insert(0, (path.clone(), topology));
+    }
+    if let Some(path) = &input.metadata {
+        let content =
+            read_input_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        let metadata =
+            parse_csv_metadata(&content, &csv).with_context(|| format!("parse error in {path}"))?;
+        docs.push((path.clone(), metadata));
+    }
+    let doc = merge_documents(docs).context("cannot merge inputs")?;
+    if let Some(station) = doc
+        .metadata
+        .keys()
+        .find(|id| !doc.stations.iter().any(|s| s.id == **id))
+    {
+        anyhow::bail!("metadata references unknown station id {}", station.0);
+    }
+    if !doc.warnings.is_empty() {
+        eprintln!(
+            "warning: skipped {} invalid report line(s)",
+            doc.warnings.len()
+        );
+    }
+    Ok(doc)
+}
+
+fn uptime_config(policy: &PolicyArgs, doc: &mut ParsedInput) -> anyhow::Result<UptimeConfig> {
+    let mut status_policy = StatusPolicy::default();
+    for spec in &policy.classify {
+        status_policy
+            .apply_spec(spec)
+            .context("invalid --classify")?;
+    }
+    let excuse_policy = match policy.excuse.as_deref() {
+        None => ExcusePolicy::default(),
+        Some(list) => ExcusePolicy::from_list(list).context("invalid --excuse")?,
+    };
+    Ok(UptimeConfig {
+        status_policy,
+        assignments: std::mem::take(&mut doc.assignments),
+        maintenance: std::mem::take(&mut doc.maintenance),
+        outages: std::mem::take(&mut doc.outages),
+        excuse_policy,
+        connector_rollup: policy.connector_rollup.into(),
+        charger_rollup: policy.charger_rollup.into(),
+    })
+}
+
+fn print_json(value: &impl Serialize) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// One JSON row of `compute`: the unit, its `StationUptime` fields and the
+/// station's metadata.
+#[derive(Serialize)]
+struct UptimeRow<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    charger: Option<ChargerId>,
+    /// Set at `--level connector`, where a charger-wide unit has a `null` connector.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connector: Option<Option<ConnectorId>>,
+    #[serde(flatten)]
+    uptime: UptimeJson<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a StationMetadata>,
+}
+
+/// A `StationResult` as flat JSON fields: the computed figures, or the
+/// station with a `null` percent.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum UptimeJson<'a> {
+    Computed(&'a StationUptime),
+    NoData {
+        station: StationId,
+        percent: Option<u8>,
+    },
+}
+
+impl<'a> UptimeRow<'a> {
+    fn new(result: &'a StationResult, metadata: &'a BTreeMap<StationId, StationMetadata>) -> Self {
+        UptimeRow {
+            charger: None,
+            connector: None,
+            uptime: match result {
+                StationResult::Computed(r) => UptimeJson::Computed(r),
+                StationResult::NoData(station) => UptimeJson::NoData {
+                    station: *station,
+                    percent: None,
+                },
+            },
+            metadata: metadata.get(&result.station()),
         }
+    }
+}
+
+/// Names of the `PERCENTILES` entries in `GroupUptime::percentiles`, in order.
+fn percentile_names() -> Vec<String> {
+    PERCENTILES.iter().map(|p| format!("p{p}")).collect()
+}
+
+/// Compute and print uptimes, flagging stations below their SLA target.
+fn compute(cli: &Cli, args: &ComputeArgs) -> anyhow::Result<Outcome> {
+    let level = match args.level {
+        Level::Station => None,
+        Level::Charger => Some(UnitLevel::Charger),
+        Level::Connector => Some(UnitLevel::Connector),
     };
+    if let Some(level) = level {
+        if args.sla.is_some() || args.sla_config.is_some() {
+            anyhow::bail!("--sla and --sla-config only apply at --level station");
+        }
+        if args.group_by.is_some() {
+            anyhow::bail!("--group-by only applies at --level station");
+        }
+        return compute_units(cli, args, level);
+    }
 
-    match compute_station_uptime(&stations, &reports) {
-        Ok(mut results) => {
-            results.sort_by_key(|(sid, _)| *sid);
-            for (sid, pct) in results {
-                println!("{} {}", sid.0, pct);
+    let mut sla = match &args.sla_config {
+        None => SlaPolicy::default(),
+        Some(path) => {
+            let config = fs::read_to_string(path).context("failed to read SLA config")?;
+            parse_sla_config(&config)?
+        }
+    };
+    if let Some(target) = &args.sla {
+        sla.default_target = Some(parse_target(target)?);
+    }
+
+    let mut doc = load_input(cli, &args.input)?;
+    let config = uptime_config(&args.policy, &mut doc)?;
+
+    let results = compute_station_results(&doc.stations, &doc.reports, &config);
+    let mut computed: Vec<StationUptime> = Vec::with_capacity(results.len());
+    for result in &results {
+        match result {
+            StationResult::Computed(r) => computed.push(r.clone()),
+            StationResult::NoData(id) if !args.allow_missing => {
+                return Err(UptimeError::NoReporting(*id)).context("uptime error");
+            }
+            StationResult::NoData(_) => {}
+        }
+    }
+    let breaches = sla.breaches(&computed);
+
+    if let Some(keys) = &args.group_by {
+        let keys: Vec<String> = keys.split(',').map(|k| k.trim().to_string()).collect();
+        if keys.iter().any(String::is_empty) {
+            anyhow::bail!("invalid --group-by: empty key");
+        }
+        if let Some(key) = keys
+            .iter()
+            .find(|key| !doc.metadata.values().any(|m| m.get(key).is_some()))
+        {
+            anyhow::bail!("invalid --group-by: no station has metadata key '{key}'");
+        }
+        let groups = aggregate_results(&results, &doc.metadata, &keys);
+        print_groups(cli, &keys, &groups, &breaches)?;
+    } else {
+        print_station_results(cli, args, &doc, &results, &breaches)?;
+    }
+
+    for b in &breaches {
+        eprintln!(
+            "SLA breach: station {} uptime {:.2}% below target {}%",
+            b.station.0, b.actual, b.target
+        );
+    }
+    Ok(if breaches.is_empty() {
+        Outcome::Ok
+    } else {
+        Outcome::SlaBreach
+    })
+}
+
+/// Print one line, JSON object or CSV row per station.
+fn print_station_results(
+    cli: &Cli,
+    args: &ComputeArgs,
+    doc: &ParsedInput,
+    results: &[StationResult],
+    breaches: &[SlaBreach],
+) -> anyhow::Result<()> {
+    match cli.format {
+        OutputFormat::Text => {
+            for result in results {
+                match result {
+                    StationResult::Computed(r) => {
+                        println!("{} {}", r.station.0, uptime_text(r, args.breakdown))
+                    }
+                    StationResult::NoData(id) => println!("{} N/A", id.0),
+                }
             }
-            ExitCode::SUCCESS
         }
+        OutputFormat::Json => {
+            let stations: Vec<_> = results
+                .iter()
+                .map(|result| UptimeRow::new(result, &doc.metadata))
+                .collect();
+            print_json(&json!({"stations": stations, "sla_breaches": breaches}))?;
+        }
+        OutputFormat::Csv => {
+            let columns = metadata_columns(&doc.metadata);
+            let rows = results.iter().map(|result| {
+                let mut row = vec![result.station().0.to_string()];
+                row.extend(uptime_cells(result));
+                row.extend(metadata_cells(&doc.metadata, result.station(), &columns));
+                row
+            });
+            let header = [&["station"][..], &UPTIME_COLUMNS].concat();
+            print_csv(&header, &columns, rows)?;
+        }
+    }
+    Ok(())
+}
+
+/// `--group-by` JSON output. A typed struct rather than `json!`, whose
+/// `Value` cannot hold the `u128` time sums.
+#[derive(Serialize)]
+struct GroupsJson<'a> {
+    groups: &'a [GroupUptime],
+    sla_breaches: &'a [SlaBreach],
+}
+
+/// Print the figures of each `--group-by` group, labelled by its key values.
+fn print_groups(
+    cli: &Cli,
+    keys: &[String],
+    groups: &[GroupUptime],
+    breaches: &[SlaBreach],
+) -> anyhow::Result<()> {
+    let percentile_names = percentile_names();
+    let optional = |value: Option<u8>| value.map_or(String::new(), |v| v.to_string());
+    let key_value = |group: &GroupUptime, key: &str| group.key[key].clone();
+    match cli.format {
+        OutputFormat::Text => {
+            for group in groups {
+                let label: Vec<String> = keys
+                    .iter()
+                    .map(|key| {
+                        let value = key_value(group, key);
+                        format!("{key}={}", value.as_deref().unwrap_or("-"))
+                    })
+                    .collect();
+                let Some(uptime) = group.uptime else {
+                    println!(
+                        "{} stations=0 no_data={} N/A",
+                        label.join(" "),
+                        group.no_data
+                    );
+                    continue;
+                };
+                let percentiles: String = percentile_names
+                    .iter()
+                    .map(|name| format!(" {name}={}", optional(group.percentiles[name])))
+                    .collect();
+                println!(
+                    "{} stations={} no_data={} uptime={uptime:.2} min={}{percentiles} max={}",
+                    label.join(" "),
+                    group.stations,
+                    group.no_data,
+                    optional(group.min),
+                    optional(group.max),
+                );
+            }
+        }
+        OutputFormat::Json => print_json(&GroupsJson {
+            groups,
+            sla_breaches: breaches,
+        })?,
+        OutputFormat::Csv => {
+            let mut header: Vec<&str> = keys.iter().map(String::as_str).collect();
+            header.extend([
+                "stations",
+                "no_data",
+                "available",
+                "reported",
+                "uptime",
+                "min",
+            ]);
+            header.extend(percentile_names.iter().map(String::as_str));
+            header.push("max");
+            let rows = groups.iter().map(|group| {
+                let mut row: Vec<String> = keys
+                    .iter()
+                    .map(|key| key_value(group, key).unwrap_or_default())
+                    .collect();
+                row.extend([
+                    group.stations.to_string(),
+                    group.no_data.to_string(),
+                    group.available.to_string(),
+                    group.reported.to_string(),
+                    group.uptime.map_or(String::new(), |u| format!("{u:.2}")),
+                    optional(group.min),
+                ]);
+                row.extend(
+                    percentile_names
+                        .iter()
+                        .map(|name| optional(group.percentiles[name])),
+                );
+                row.push(optional(group.max));
+                row
+            });
+            print_csv(&header, &[], rows)?;
+        }
+    }
+    Ok(())
+}
+
+/// Print per-charger or per-connector uptimes. Units without reports print
+/// `N/A` rather than failing, since a station's other units still count.
+fn compute_units(cli: &Cli, args: &ComputeArgs, level: UnitLevel) -> anyhow::Result<Outcome> {
+    let mut doc = load_input(cli, &args.input)?;
+    let config = uptime_config(&args.policy, &mut doc)?;
+    let results = compute_unit_results(&doc.stations, &doc.reports, &config, level);
+
+    match cli.format {
+        OutputFormat::Text => {
+            for unit in &results {
+                let station = unit.result.station().0;
+                let token = ChargerToken {
+                    charger: unit.charger,
+                    connector: unit.connector,
+                };
+                match &unit.result {
+                    StationResult::Computed(r) => {
+                        println!("{station} {token} {}", uptime_text(r, args.breakdown))
+                    }
+                    StationResult::NoData(_) => println!("{station} {token} N/A"),
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let key = match level {
+                UnitLevel::Charger => "chargers",
+                UnitLevel::Connector => "connectors",
+            };
+            let rows: Vec<_> = results
+                .iter()
+                .map(|unit| UptimeRow {
+                    charger: Some(unit.charger),
+                    connector: (level == UnitLevel::Connector).then_some(unit.connector),
+                    ..UptimeRow::new(&unit.result, &doc.metadata)
+                })
+                .collect();
+            print_json(&json!({ key: rows }))?;
+        }
+        OutputFormat::Csv => {
+            let columns = metadata_columns(&doc.metadata);
+            let rows = results.iter().map(|unit| {
+                let station = unit.result.station();
+                let mut row = vec![station.0.to_string(), unit.charger.0.to_string()];
+                if level == UnitLevel::Connector {
+                    row.push(unit.connector.map_or(String::new(), |c| c.0.to_string()));
+                }
+                row.extend(uptime_cells(&unit.result));
+                row.extend(metadata_cells(&doc.metadata, station, &columns));
+                row
+            });
+            let leading: &[&str] = match level {
+                UnitLevel::Charger => &["station", "charger"],
+                UnitLevel::Connector => &["station", "charger", "connector"],
+            };
+            print_csv(&[leading, &UPTIME_COLUMNS].concat(), &columns, rows)?;
+        }
+    }
+    Ok(Outcome::Ok)
+}
+
+/// The percentage, followed by the `--breakdown` figures when asked for.
+fn uptime_text(r: &StationUptime, breakdown: bool) -> String {
+    if !breakdown {
+        return r.percent.to_string();
+    }
+    let statuses: String = r
+        .by_status
+        .iter()
+        .map(|(status, time)| format!(" {status}={time}"))
+        .collect();
+    format!(
+        "{} raw={} available={} reported={} excluded={} excused={}{}",
+        r.percent, r.raw_percent, r.available, r.reported, r.excluded, r.excused, statuses
+    )
+}
+
+/// Metadata columns for CSV output: the standard fields, then every tag in use.
+fn metadata_columns(metadata: &BTreeMap<StationId, StationMetadata>) -> Vec<String> {
+    let tags: BTreeSet<&String> = metadata.values().flat_map(|m| m.tags.keys()).collect();
+    StationMetadata::FIELDS
+        .iter()
+        .map(|field| field.to_string())
+        .chain(tags.into_iter().cloned())
+        .collect()
+}
+
+fn metadata_cells(
+    metadata: &BTreeMap<StationId, StationMetadata>,
+    station: StationId,
+    columns: &[String],
+) -> Vec<String> {
+    let metadata = metadata.get(&station);
+    columns
+        .iter()
+        .map(|key| {
+            metadata
+                .and_then(|m| m.get(key))
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect()
+}
+
+/// The uptime figures of a CSV row, left empty without data.
+fn uptime_cells(result: &StationResult) -> Vec<String> {
+    match result {
+        StationResult::Computed(r) => [
+            u64::from(r.percent),
+            u64::from(r.raw_percent),
+            r.available,
+            r.reported,
+            r.excluded,
+            r.excused,
+        ]
+        .iter()
+        .map(u64::to_string)
+        .collect(),
+        StationResult::NoData(_) => vec![String::new(); UPTIME_COLUMNS.len()],
+    }
+}
+
+/// Uptime columns of CSV rows; metadata keys may not reuse them (see
+/// `StationMetadata::RESERVED_KEYS`).
+const UPTIME_COLUMNS: [&str; 6] = [
+    "uptime",
+    "raw_uptime",
+    "available",
+    "reported",
+    "excluded",
+    "excused",
+];
+
+/// Print `--format csv` output: `columns`, then the metadata columns.
+fn print_csv(
+    columns: &[&str],
+    metadata_columns: &[String],
+    rows: impl Iterator<Item = Vec<String>>,
+) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+    let header = columns
+        .iter()
+        .copied()
+        .chain(metadata_columns.iter().map(String::as_str));
+    writer.write_record(header)?;
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Parse the input and print a data-quality report. Only hard parse errors
+/// make the input invalid; quality findings are informational.
+fn validate(cli: &Cli, args: &ValidateArgs) -> anyhow::Result<Outcome> {
+    reject_csv_output(cli, "validate")?;
+    let doc = match load_input(cli, &args.input) {
+        Ok(doc) => doc,
+        // Under --compat, invalid input prints `ERROR` like every other command
+        Err(e) if cli.compat => return Err(e),
         Err(e) => {
-            eprintln!("uptime error: {e}");
-            println!("ERROR");
-            ExitCode::SUCCESS
+            let error = format!("{e:#}");
+            match cli.format {
+                OutputFormat::Text => println!("invalid: {error}"),
+                OutputFormat::Json => print_json(&json!({"valid": false, "error": error}))?,
+                OutputFormat::Csv => unreachable!("rejected above"),
+            }
+            return Ok(Outcome::InvalidInput);
         }
+    };
+
+    let now = match args.now {
+        Some(now) => now,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(u64::MAX),
+    };
+    let config = QualityConfig {
+        max_gap: args.max_gap,
+        now: Some(now),
+    };
+    let quality = assess_quality(&doc.stations, &doc.assignments, &doc.reports, &config);
+
+    match cli.format {
+        OutputFormat::Text => {
+            println!(
+                "valid: {} stations, {} reports",
+                doc.stations.len(),
+                doc.reports.len()
+            );
+            if quality.is_clean() {
+                println!("no data-quality issues found");
+            }
+            for c in &quality.chargers_without_reports {
+                println!("charger without reports: {}", c.0);
+            }
+            for s in &quality.stations_without_reports {
+                println!("station without reports: {}", s.0);
+            }
+            for o in &quality.overlapping_reports {
+                println!("overlapping reports: {}", describe_overlap(o));
+            }
+            for o in &quality.conflicting_reports {
+                println!("conflicting reports: {}", describe_overlap(o));
+            }
+            for d in &quality.duplicate_reports {
+                println!(
+                    "duplicate report: charger {} [{}, {}) {} x{}",
+                    d.report.token(),
+                    d.report.interval.start,
+                    d.report.interval.end,
+                    d.report.status,
+                    d.count
+                );
+            }
+            for gap in &quality.long_gaps {
+                println!(
+                    "long gap: charger {} [{}, {})",
+                    gap.charger.0, gap.interval.start, gap.interval.end
+                );
+            }
+            for r in &quality.future_reports {
+                println!(
+                    "future report: charger {} [{}, {}) {}",
+                    r.token(),
+                    r.interval.start,
+                    r.interval.end,
+                    r.status
+                );
+            }
+        }
+        OutputFormat::Json => print_json(&json!({
+            "valid": true,
+            "error": null,
+            "stations": doc.stations.len(),
+            "reports": doc.reports.len(),
+            "quality": quality,
+        }))?,
+        OutputFormat::Csv => unreachable!("rejected above"),
+    }
+    Ok(Outcome::Ok)
+}
+
+/// `--format csv` is only defined for the tabular outputs of compute and outages.
+fn reject_csv_output(cli: &Cli, command: &str) -> anyhow::Result<()> {
+    if cli.format == OutputFormat::Csv {
+        anyhow::bail!("--format csv is not supported by {command}");
+    }
+    Ok(())
+}
+
+fn describe_overlap(o: &ReportOverlap) -> String {
+    format!(
+        "charger {} [{}, {}) {} and [{}, {}) {}",
+        o.first.token(),
+        o.first.interval.start,
+        o.first.interval.end,
+        o.first.status,
+        o.second.interval.start,
+        o.second.interval.end,
+        o.second.status
+    )
+}
+
+/// Print each station's down periods.
+fn outages(cli: &Cli, args: &OutagesArgs) -> anyhow::Result<Outcome> {
+    let mut doc = load_input(cli, &args.input)?;
+    let config = uptime_config(&args.policy, &mut doc)?;
+    let outages = compute_station_outages(&doc.stations, &doc.reports, &config);
+
+    match cli.format {
+        OutputFormat::Text => {
+            for (sid, intervals) in &outages {
+                for iv in intervals {
+                    println!("{} {} {}", sid.0, iv.start, iv.end);
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let outages: Vec<_> = outages
+                .iter()
+                .flat_map(|(sid, intervals)| {
+                    intervals
+                        .iter()
+                        .map(|iv| json!({"station": sid.0, "start": iv.start, "end": iv.end}))
+                })
+                .collect();
+            print_json(&json!({ "outages": outages }))?;
+        }
+        OutputFormat::Csv => {
+            let columns = metadata_columns(&doc.metadata);
+            let rows = outages.iter().flat_map(|(sid, intervals)| {
+                intervals.iter().map(|iv| {
+                    let mut row = vec![sid.0.to_string(), iv.start.to_string(), iv.end.to_string()];
+                    row.extend(metadata_cells(&doc.metadata, *sid, &columns));
+                    row
+                })
+            });
+            print_csv(&["station", "start", "end"], &columns, rows)?;
+        }
+    }
+    Ok(Outcome::Ok)
+}
+
+/// `stats` JSON output, typed like `GroupsJson` for the `u128` fleet sums.
+#[derive(Serialize)]
+struct StatsJson<'a> {
+    stations: usize,
+    chargers: usize,
+    reports: usize,
+    maintenance_windows: usize,
+    outages: usize,
+    first_start: Option<u64>,
+    last_end: Option<u64>,
+    fleet: &'a FleetSummary,
+}
+
+/// Print counts and the covered time range of the input, followed by the
+/// fleet's uptime distribution.
+fn stats(cli: &Cli, args: &StatsArgs) -> anyhow::Result<Outcome> {
+    reject_csv_output(cli, "stats")?;
+    let target = parse_target(&args.below).context("invalid --below")?;
+    let mut doc = load_input(cli, &args.input)?;
+    let chargers: usize = doc.stations.iter().map(|s| s.chargers.len()).sum();
+    let first_start = doc.reports.iter().map(|r| r.interval.start).min();
+    let last_end = doc.reports.iter().map(|r| r.interval.end).max();
+    let maintenance_windows = doc.maintenance.len();
+    let outages = doc.outages.len();
+
+    let config = uptime_config(&args.policy, &mut doc)?;
+    let results = compute_station_results(&doc.stations, &doc.reports, &config);
+    let summary = fleet_summary(&results, target, args.worst);
+    let fleet = &summary.fleet;
+
+    match cli.format {
+        OutputFormat::Text => {
+            println!("stations {}", doc.stations.len());
+            println!("chargers {chargers}");
+            println!("reports {}", doc.reports.len());
+            println!("maintenance_windows {maintenance_windows}");
+            println!("outages {outages}");
+            if let (Some(start), Some(end)) = (first_start, last_end) {
+                println!("time_range {start} {end}");
+            }
+            println!("stations_without_data {}", fleet.no_data);
+            let Some(uptime) = fleet.uptime else {
+                println!("fleet_uptime N/A");
+                return Ok(Outcome::Ok);
+            };
+            println!("fleet_uptime {uptime:.2}");
+            for name in percentile_names() {
+                if let Some(value) = fleet.percentiles[&name] {
+                    println!("{name} {value}");
+                }
+            }
+            println!("below {target} {}", summary.below_target);
+            for worst in &summary.worst {
+                println!("worst {} {:.2}", worst.station.0, worst.uptime);
+            }
+        }
+        OutputFormat::Json => print_json(&StatsJson {
+            stations: doc.stations.len(),
+            chargers,
+            reports: doc.reports.len(),
+            maintenance_windows,
+            outages,
+            first_start,
+            last_end,
+            fleet: &summary,
+        })?,
+        OutputFormat::Csv => unreachable!("rejected above"),
+    }
+    Ok(Outcome::Ok)
+}
+
+/// Re-emit the input in the requested input format.
+fn convert(cli: &Cli, args: &ConvertArgs) -> anyhow::Result<Outcome> {
+    let doc = load_input(cli, &args.input)?;
+    if args.to != InputFormat::Text
+        && !(doc.assignments.is_empty()
+            && doc.maintenance.is_empty()
+            && doc.outages.is_empty()
+            && doc.metadata.is_empty())
+    {
+        anyhow::bail!(
+            "charger assignments, maintenance windows, outages and station metadata can only be written with --to text"
+        );
+    }
+    let out = match args.to {
+        InputFormat::Text => write_document(&doc),
+        InputFormat::Csv => write_csv_reports(&doc.reports, &cli.csv.options()?),
+        InputFormat::Json => write_json_document(&doc.stations, &doc.reports),
+        InputFormat::Ndjson => write_ndjson_reports(&doc.reports),
+        InputFormat::Ocpp | InputFormat::Heartbeat => {
+            anyhow::bail!("converting to {} is not supported", args.to)
+        }
+    };
+
+    let reports_only = matches!(args.to, InputFormat::Csv | InputFormat::Ndjson);
+    match (&args.stations_output, reports_only) {
+        (Some(path), true) => {
+            let stations = if path.ends_with(".csv") {
+                write_csv_stations(&doc.stations, &cli.csv.options()?)
+            } else {
+                write_document(&ParsedInput {
+                    stations: doc.stations.clone(),
+                    ..ParsedInput::default()
+                })
+            };
+            fs::write(path, stations).context("failed to write stations")?;
+        }
+        (None, true) => {
+            anyhow::bail!(
+                "--to {} holds reports only; pass --stations-output",
+                args.to
+            )
+        }
+        (Some(_), false) => {
+            anyhow::bail!("--to {} already includes the stations", args.to)
+        }
+        (None, false) => {}
+    }
+
+    match &args.output {
+        Some(path) => fs::write(path, out).context("failed to write output")?,
+        None => print!("{out}"),
     }
+    Ok(Outcome::Ok)
 }
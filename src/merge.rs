@@ -0,0 +1,307 @@
+use crate::parser::ParsedInput;
+use crate::types::{ChargerAssignment, ChargerId, StationId};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A line in a named input, printed as `source:line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub source: String,
+    pub line: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.source, self.line)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MergeError {
+    #[error("station {} has different chargers at {first} and {second}", .station.0)]
+    ConflictingStation {
+        station: StationId,
+        first: Location,
+        second: Location,
+    },
+    #[error(
+        "charger {} belongs to station {} at {first} and to station {} at {second}",
+        .charger.0,
+        .first_station.0,
+        .second_station.0
+    )]
+    ConflictingCharger {
+        charger: ChargerId,
+        first_station: StationId,
+        first: Location,
+        second_station: StationId,
+        second: Location,
+    },
+    #[error(
+        "charger {} belongs to station {} at {first} and is assigned to station {} at {second}",
+        .charger.0,
+        .station.0,
+        .assigned_station.0
+    )]
+    AssignedFixedCharger {
+        charger: ChargerId,
+        station: StationId,
+        first: Location,
+        assigned_station: StationId,
+        second: Location,
+    },
+    #[error("charger {} has overlapping assignments at {first} and {second}", .charger.0)]
+    OverlappingAssignments {
+        charger: ChargerId,
+        first: Location,
+        second: Location,
+    },
+    #[error("station {} has different '{key}' metadata in {first} and {second}", .station.0)]
+    ConflictingMetadata {
+        station: StationId,
+        key: String,
+        first: String,
+        second: String,
+    },
+}
+
+/// Merge documents parsed from several named inputs into one.
+///
+/// A station defined identically (same chargers, in any order) in several
+/// inputs is kept once, and so is an identical charger assignment. Across
+/// inputs, as within one, a charger's assignments must not overlap and a
+/// charger listed under a station cannot also be assigned. Station metadata
+/// is combined key by key; a key set to different values in two inputs is an
+/// error. Reports, windows and warnings are concatenated in input order.
+/// Header fields are taken from the first input that sets them.
+pub fn merge_documents(inputs: Vec<(String, ParsedInput)>) -> Result<ParsedInput, MergeError> {
+    let mut merged = ParsedInput::default();
+    let mut station_at: BTreeMap<StationId, (usize, Location)> = BTreeMap::new();
+    let mut charger_at: BTreeMap<ChargerId, (StationId, Location)> = BTreeMap::new();
+    let mut metadata_from: BTreeMap<(StationId, String), String> = BTreeMap::new();
+    let mut assignment_at: Vec<(ChargerAssignment, Location)> = Vec::new();
+
+    for (source, doc) in inputs {
+        let header = doc.header;
+        merged.header.source = merged.header.source.or(header.source);
+        merged.header.exported_at = merged.header.exported_at.or(header.exported_at);
+        merged.header.time_unit = merged.header.time_unit.or(header.time_unit);
+        merged.header.format_version = merged.header.format_version.or(header.format_version);
+        for (key, value) in header.extra {
+            merged.header.extra.entry(key).or_insert(value);
+        }
+
+        for station in doc.stations {
+            let location = Location {
+                source: source.clone(),
+                line: doc.station_lines.get(&station.id).copied().unwrap_or(0),
+            };
+            if let Some((index, first)) = station_at.get(&station.id) {
+                let mut existing = merged.stations[*index].chargers.clone();
+                let mut incoming = station.chargers.clone();
+                existing.sort();
+                incoming.sort();
+                if existing != incoming {
+                    return Err(MergeError::ConflictingStation {
+                        station: station.id,
+                        first: first.clone(),
+                        second: location,
+                    });
+                }
+                continue;
+            }
+            for charger in &station.chargers {
+                if let Some((first_station, first)) = charger_at.get(charger) {
+                    return Err(MergeError::ConflictingCharger {
+                        charger: *charger,
+                        first_station: *first_station,
+                        first: first.clone(),
+                        second_station: station.id,
+                        second: location,
+                    });
+                }
+                charger_at.insert(*charger, (station.id, location.clone()));
+            }
+            merged.station_lines.insert(station.id, location.line);
+            station_at.insert(station.id, (merged.stations.len(), location));
+            merged.stations.push(station);
+        }
+
+        for (station, metadata) in doc.metadata {
+            let target = merged.metadata.entry(station).or_default();
+            for (key, value) in metadata.entries() {
+                match target.get(key) {
+                    Some(existing) if existing != value => {
+                        return Err(MergeError::ConflictingMetadata {
+                            station,
+                            key: key.to_string(),
+                            first: metadata_from[&(station, key.to_string())].clone(),
+                            second: source.clone(),
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        target.set(key, value.to_string());
+                        metadata_from.insert((station, key.to_string()), source.clone());
+                    }
+                }
+            }
+        }
+
+        for (i, assignment) in doc.assignments.into_iter().enumerate() {
+            if assignment_at.iter().any(|(a, _)| *a == assignment) {
+                continue;
+            }
+            let location = Location {
+                source: source.clone(),
+                line: doc.assignment_lines.get(i).copied().unwrap_or(0),
+            };
+            assignment_at.push((assignment, location));
+        }
+        merged.reports.extend(doc.reports);
+        merged.maintenance.extend(doc.maintenance);
+        merged.outages.extend(doc.outages);
+        merged.warnings.extend(doc.warnings);
+    }
+
+    check_assignments(&mut assignment_at, &charger_at)?;
+    for (assignment, location) in assignment_at {
+        merged.assignments.push(assignment);
+        merged.assignment_lines.push(location.line);
+    }
+    Ok(merged)
+}
+
+/// Apply the single-file assignment rules to the assignments of all inputs.
+/// Sorts `assignments` by charger and start.
+fn check_assignments(
+    assignments: &mut [(ChargerAssignment, Location)],
+    fixed: &BTreeMap<ChargerId, (StationId, Location)>,
+) -> Result<(), MergeError> {
+    for (assignment, location) in assignments.iter() {
+        if let Some((station, first)) = fixed.get(&assignment.charger) {
+            return Err(MergeError::AssignedFixedCharger {
+                charger: assignment.charger,
+                station: *station,
+                first: first.clone(),
+                assigned_station: assignment.station,
+                second: location.clone(),
+            });
+        }
+    }
+    assignments.sort_by_key(|(a, _)| (a.charger, a.interval.start));
+    for pair in assignments.windows(2) {
+        let ((a, first), (b, second)) = (&pair[0], &pair[1]);
+        if a.charger == b.charger && b.interval.start < a.interval.end {
+            return Err(MergeError::OverlappingAssignments {
+                charger: a.charger,
+                first: first.clone(),
+                second: second.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+
+    fn doc(input: &str) -> ParsedInput {
+        parse_document(input).unwrap()
+    }
+
+    #[test]
+    fn identical_stations_are_deduplicated() {
+        let a = doc("[Stations]\n1 100 101\n[Charger Availability Reports]\n100 0 10 true\n");
+        let b =
+            doc("[Stations]\n1 101 100\n2 200\n[Charger Availability Reports]\n200 0 10 false\n");
+        let merged = merge_documents(vec![("a".into(), a), ("b".into(), b)]).unwrap();
+        assert_eq!(merged.stations.len(), 2);
+        assert_eq!(merged.reports.len(), 2);
+    }
+
+    #[test]
+    fn conflicts_cite_both_locations() {
+        let a = doc("[Stations]\n1 100\n[Charger Availability Reports]\n100 0 10 true\n");
+        let b =
+            doc("# day 2\n[Stations]\n1 100 101\n[Charger Availability Reports]\n100 0 10 true\n");
+        let err =
+            merge_documents(vec![("a.txt".into(), a.clone()), ("b.txt".into(), b)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "station 1 has different chargers at a.txt:2 and b.txt:3"
+        );
+
+        let c = doc("[Stations]\n2 100\n[Charger Availability Reports]\n100 0 10 true\n");
+        let err = merge_documents(vec![("a.txt".into(), a), ("c.txt".into(), c)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "charger 100 belongs to station 1 at a.txt:2 and to station 2 at c.txt:2"
+        );
+    }
+
+    #[test]
+    fn assignments_are_checked_across_inputs() {
+        let fixed = doc("[Stations]\n1 100 200\n[Charger Availability Reports]\n200 0 10 true\n");
+        let assigned = doc(
+            "[Stations]\n2\n[Charger Assignments]\n200 2 0\n300 2 0 50\n[Charger Availability Reports]\n200 0 10 true\n",
+        );
+        let err = merge_documents(vec![
+            ("b.txt".into(), assigned.clone()),
+            ("a.txt".into(), fixed),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "charger 200 belongs to station 1 at a.txt:2 and is assigned to station 2 at b.txt:4"
+        );
+
+        let overlapping = doc(
+            "[Stations]\n3\n[Charger Assignments]\n300 3 40 90\n[Charger Availability Reports]\n300 0 10 true\n",
+        );
+        let err = merge_documents(vec![
+            ("b.txt".into(), assigned.clone()),
+            ("c.txt".into(), overlapping),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "charger 300 has overlapping assignments at b.txt:5 and c.txt:4"
+        );
+
+        // The same assignment in two inputs is kept once
+        let merged = merge_documents(vec![
+            ("b.txt".into(), assigned.clone()),
+            ("b2.txt".into(), assigned),
+        ])
+        .unwrap();
+        assert_eq!(merged.assignments.len(), 2);
+        assert_eq!(merged.assignment_lines, vec![4, 5]);
+    }
+
+    #[test]
+    fn metadata_is_combined_by_key() {
+        let a = doc(
+            "[Stations]\n1 100\n[Station Metadata]\n1 name Depot\n[Charger Availability Reports]\n100 0 10 true\n",
+        );
+        let b = doc(
+            "[Stations]\n1 100\n[Station Metadata]\n1 name Depot\n1 region north\n[Charger Availability Reports]\n100 0 10 true\n",
+        );
+        let merged =
+            merge_documents(vec![("a.txt".into(), a.clone()), ("b.txt".into(), b)]).unwrap();
+        let metadata = &merged.metadata[&StationId(1)];
+        assert_eq!(metadata.name.as_deref(), Some("Depot"));
+        assert_eq!(metadata.region.as_deref(), Some("north"));
+
+        let c = doc(
+            "[Stations]\n1 100\n[Station Metadata]\n1 name Yard\n[Charger Availability Reports]\n100 0 10 true\n",
+        );
+        let err = merge_documents(vec![("a.txt".into(), a), ("c.txt".into(), c)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "station 1 has different 'name' metadata in a.txt and c.txt"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use crate::parser::{ParseOptions, ParseWarning, ParsedInput};
+use crate::types::{ChargerId, ChargerReport, ChargerStatus, ConnectorId, Interval};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufRead;
+
+#[derive(thiserror::Error, Debug)]
+pub enum OcppError {
+    #[error("invalid status notification: {0}")]
+    InvalidRecord(String),
+    #[error("failed to read input: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Settings for turning status notifications into reports.
+#[derive(Debug, Clone, Default)]
+pub struct OcppOptions {
+    /// Where the last status of each connector stops holding. Defaults to the
+    /// latest timestamp in the log.
+    pub until: Option<u64>,
+}
+
+/// One logged StatusNotification. OCPP 1.6 sends `status` and `connectorId`;
+/// 2.0.1 sends `connectorStatus`, `evseId` and `connectorId`. Lines whose
+/// `action` names another message are skipped before decoding.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+    charger: ChargerId,
+    #[serde(default)]
+    evse_id: Option<u32>,
+    #[serde(default)]
+    connector_id: u32,
+    #[serde(alias = "connectorStatus")]
+    status: String,
+    timestamp: Timestamp,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Nanos(u64),
+    Rfc3339(String),
+}
+
+/// Map an OCPP 1.6 or 2.0.1 connector status onto `ChargerStatus`. With the
+/// default status policy, everything but `Unavailable` and `Faulted` is up.
+pub fn map_status(status: &str) -> Option<ChargerStatus> {
+    Some(match status {
+        "Available" | "Reserved" => ChargerStatus::Idle,
+        "Preparing" | "Charging" | "SuspendedEV" | "SuspendedEVSE" | "Finishing" | "Occupied" => {
+            ChargerStatus::Charging
+        }
+        "Unavailable" => ChargerStatus::Down,
+        "Faulted" => ChargerStatus::Faulted,
+        _ => return None,
+    })
+}
+
+/// Import a newline-delimited JSON log of StatusNotification messages, e.g.
+/// `{"charger": 100, "connectorId": 1, "status": "Charging", "timestamp": "2024-05-01T12:00:00Z"}`.
+///
+/// Each connector's status holds until its next notification; the last one
+/// holds until `OcppOptions::until`. Reports carry the notification's
+/// `connectorId`, except for connector 0, which reports for the whole charger.
+/// Chargers must be declared in `topology`; invalid lines fail the import or,
+/// with `options.lenient`, become warnings.
+pub fn import_status_notifications(
+    reader: impl BufRead,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+    ocpp: &OcppOptions,
+) -> Result<ParsedInput, OcppError> {
+    let known = topology.known_chargers();
+
+    let mut doc = ParsedInput::default();
+    // (charger, evse, connector) -> (timestamp, status) in log order
+    let mut streams: BTreeMap<(ChargerId, u32, u32), Vec<(u64, ChargerStatus)>> = BTreeMap::new();
+    let mut latest = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_notification(&line, &known) {
+            Ok(None) => {}
+            Ok(Some((key, at, status))) => {
+                latest = latest.max(at);
+                streams.entry(key).or_default().push((at, status));
+            }
+            Err(message) => {
+                let message = format!("line {line_no}: {message}");
+                if !options.lenient {
+                    return Err(OcppError::InvalidRecord(message));
+                }
+                doc.warnings.push(ParseWarning {
+                    line: line_no,
+                    message,
+                });
+            }
+        }
+    }
+
+    let until = ocpp.until.unwrap_or(latest);
+    for ((charger, _, connector_id), mut events) in streams {
+        // Connector 0 stands for the whole charger
+        let connector = (connector_id > 0).then_some(ConnectorId(connector_id));
+        events.sort_by_key(|(at, _)| *at);
+        for (i, &(start, status)) in events.iter().enumerate() {
+            let end = events
+                .get(i + 1)
+                .map_or(until, |(next, _)| *next)
+                .min(until);
+            if end <= start {
+                continue;
+            }
+            match doc.reports.last_mut() {
+                Some(last)
+                    if last.charger == charger
+                        && last.connector == connector
+                        && last.status == status
+                        && last.interval.end == start =>
+                {
+                    last.interval.end = end
+                }
+                _ => doc.reports.push(ChargerReport {
+                    charger,
+                    connector,
+                    interval: Interval { start, end },
+                    status,
+                }),
+            }
+        }
+    }
+    if doc.reports.is_empty() {
+        return Err(OcppError::InvalidRecord("no status intervals found".into()));
+    }
+    Ok(doc)
+}
+
+type Event = ((ChargerId, u32, u32), u64, ChargerStatus);
+
+/// One log line, or `None` for messages other than StatusNotification.
+fn parse_notification(line: &str, known: &BTreeSet<ChargerId>) -> Result<Option<Event>, String> {
+    let value: serde_json::Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    if value
+        .get("action")
+        .is_some_and(|action| action != "StatusNotification")
+    {
+        return Ok(None);
+    }
+    let n: Notification = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    if !known.contains(&n.charger) {
+        return Err(format!("unknown charger id {}", n.charger.0));
+    }
+    let status =
+        map_status(&n.status).ok_or_else(|| format!("unknown OCPP status '{}'", n.status))?;
+    let at = match n.timestamp {
+        Timestamp::Nanos(at) => at,
+        Timestamp::Rfc3339(text) => chrono::DateTime::parse_from_rfc3339(&text)
+            .ok()
+            .and_then(|t| t.timestamp_nanos_opt())
+            .and_then(|ns| u64::try_from(ns).ok())
+            .ok_or_else(|| format!("invalid timestamp '{text}'"))?,
+    };
+    Ok(Some((
+        (n.charger, n.evse_id.unwrap_or(0), n.connector_id),
+        at,
+        status,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_topology;
+
+    fn import(log: &str, until: Option<u64>) -> Result<ParsedInput, OcppError> {
+        let topology = parse_topology("[Stations]\n1 100 101\n").unwrap();
+        import_status_notifications(
+            log.as_bytes(),
+            &topology,
+            &ParseOptions::default(),
+            &OcppOptions { until },
+        )
+    }
+
+    #[test]
+    fn statuses_hold_until_the_next_notification() {
+        let log = r#"{"charger": 100, "connectorId": 1, "status": "Available", "timestamp": 0}
+{"charger": 100, "connectorId": 1, "status": "Charging", "timestamp": 10}
+{"charger": 100, "connectorId": 1, "status": "Finishing", "timestamp": 20}
+{"charger": 100, "connectorId": 1, "status": "Faulted", "timestamp": 30}
+{"charger": 101, "action": "Heartbeat", "timestamp": 35}
+"#;
+        let doc = import(log, Some(50)).unwrap();
+        let got: Vec<(u64, u64, ChargerStatus)> = doc
+            .reports
+            .iter()
+            .map(|r| (r.interval.start, r.interval.end, r.status))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (0, 10, ChargerStatus::Idle),
+                (10, 30, ChargerStatus::Charging),
+                (30, 50, ChargerStatus::Faulted),
+            ]
+        );
+        assert!(
+            doc.reports
+                .iter()
+                .all(|r| r.connector == Some(ConnectorId(1)))
+        );
+    }
+
+    #[test]
+    fn ocpp_201_fields_and_rfc3339_timestamps() {
+        let log = r#"{"charger": 100, "evseId": 1, "connectorId": 1, "connectorStatus": "Occupied", "timestamp": "1970-01-01T00:00:01Z"}
+{"charger": 100, "evseId": 1, "connectorId": 1, "connectorStatus": "Unavailable", "timestamp": "1970-01-01T00:00:03+00:00"}
+"#;
+        let doc = import(log, None).unwrap();
+        // Without `until` the last status ends at the latest timestamp
+        assert_eq!(doc.reports.len(), 1);
+        assert_eq!(
+            doc.reports[0].interval,
+            Interval {
+                start: 1_000_000_000,
+                end: 3_000_000_000
+            }
+        );
+        assert_eq!(doc.reports[0].status, ChargerStatus::Charging);
+    }
+
+    #[test]
+    fn bad_lines_name_the_line() {
+        let log = "{\"charger\": 100, \"connectorId\": 1, \"status\": \"Available\", \"timestamp\": 0}\n{\"charger\": 100, \"connectorId\": 1, \"status\": \"Sleeping\", \"timestamp\": 5}\n";
+        let err = import(log, Some(10)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid status notification: line 2: unknown OCPP status 'Sleeping'"
+        );
+    }
+}
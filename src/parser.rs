@@ -1,9 +1,25 @@
-use crate::types::{ChargerId, ChargerReport, Interval, Station, StationId};
+use crate::types::{
+    ChargerAssignment, ChargerId, ChargerReport, ChargerStatus, ChargerToken, ConnectorId,
+    Interval, MaintenanceWindow, OutageCategory, OutageWindow, Station, StationId, StationMetadata,
+    TimeUnit, WindowTarget,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufRead;
+
+/// Newest input format version this build understands. Files without an
+/// `@format_version` directive are read as version 1.
+pub const CURRENT_FORMAT_VERSION: u32 = 1;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ParseError {
     #[error("invalid format: {0}")]
     InvalidFormat(String),
+    #[error(
+        "input declares format version {found}, but this build supports up to version {supported}"
+    )]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error("failed to read input: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 /// Parse the challenge input format:
@@ -12,50 +28,302 @@ pub enum ParseError {
 /// ...\n
 /// \n
 /// [Charger Availability Reports]\n
-/// <charger_id> <start> <end> <up>\n
+/// <charger_id> <start> <end> <status>\n
+///
+/// `<status>` is `true`/`false` or one of the `ChargerStatus` names.
 pub fn parse_input(input: &str) -> Result<(Vec<Station>, Vec<ChargerReport>), ParseError> {
+    let doc = parse_document(input)?;
+    Ok((doc.stations, doc.reports))
+}
+
+/// Options controlling how strictly `parse_document_with` treats its input.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Skip malformed report lines with a warning instead of failing.
+    /// Structural problems (missing sections, bad station lines) stay fatal.
+    pub lenient: bool,
+}
+
+/// A line that lenient parsing skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Metadata from the `@key value` directives at the top of a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputHeader {
+    /// `@source`: system that produced the file.
+    pub source: Option<String>,
+    /// `@exported_at`: when the file was produced, as written.
+    pub exported_at: Option<String>,
+    /// `@time_unit`: unit of the timestamps. Informational only; timestamps
+    /// are not rescaled.
+    pub time_unit: Option<TimeUnit>,
+    /// `@format_version`: version of the input format.
+    pub format_version: Option<u32>,
+    /// Any other directive, kept verbatim.
+    pub extra: BTreeMap<String, String>,
+}
+
+/// Everything a challenge-format input file can describe.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedInput {
+    pub header: InputHeader,
+    pub stations: Vec<Station>,
+    /// Line on which each station was declared (the record number for JSON).
+    pub station_lines: BTreeMap<StationId, usize>,
+    /// Optional descriptive fields per station.
+    pub metadata: BTreeMap<StationId, StationMetadata>,
+    pub assignments: Vec<ChargerAssignment>,
+    /// Line on which each assignment was declared, in `assignments` order.
+    pub assignment_lines: Vec<usize>,
+    pub reports: Vec<ChargerReport>,
+    pub maintenance: Vec<MaintenanceWindow>,
+    pub outages: Vec<OutageWindow>,
+    /// Lines skipped by lenient parsing.
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl ParsedInput {
+    /// Chargers that reports may reference: those listed under a station
+    /// plus those with assignments.
+    pub fn known_chargers(&self) -> BTreeSet<ChargerId> {
+        self.stations
+            .iter()
+            .flat_map(|s| s.chargers.iter().copied())
+            .chain(self.assignments.iter().map(|a| a.charger))
+            .collect()
+    }
+}
+
+/// Parse the input format including the optional directives and sections:
+/// @<key> <value>\n
+/// [Maintenance Windows]\n
+/// station <station_id> <start> <end>\n
+/// charger <charger_id> <start> <end>\n
+/// [Outages]\n
+/// <station|charger> <id> <start> <end> <category>\n
+/// [Charger Assignments]\n
+/// <charger_id> <station_id> <from> [<until>]\n
+/// [Station Metadata]\n
+/// <station_id> <key> <value>\n
+///
+/// A charger with assignments belongs to each assigned station only over
+/// `[from, until)`, or from `from` on when `until` is omitted. Such chargers
+/// must not also be listed in `[Stations]`, and a station line may then name
+/// no chargers as long as some assignment points at it. Metadata keys are
+/// `name`, `region`, `operator`, `network` or any tag name; each key may be
+/// set once per station.
+///
+/// Sections may appear in any order and more than once; repeated sections
+/// are concatenated. Directives must precede the first section header. `#`
+/// starts a comment, either on its own line or after whitespace at the end of
+/// a line.
+pub fn parse_document(input: &str) -> Result<ParsedInput, ParseError> {
+    parse_document_with(input, &ParseOptions::default())
+}
+
+pub fn parse_document_with(input: &str, options: &ParseOptions) -> Result<ParsedInput, ParseError> {
+    parse_document_reader(input.as_bytes(), options)
+}
+
+/// Like `parse_document_with`, reading line by line from `reader` so large or
+/// decompressed inputs need not be held in memory as text.
+pub fn parse_document_reader(
+    reader: impl BufRead,
+    options: &ParseOptions,
+) -> Result<ParsedInput, ParseError> {
+    parse_versioned(reader, options, Layout::Complete)
+}
+
+/// Parse a station topology file: a `[Stations]` section, optionally with
+/// directives and the other sections. Reports are not required.
+pub fn parse_topology(input: &str) -> Result<ParsedInput, ParseError> {
+    parse_versioned(input.as_bytes(), &ParseOptions::default(), Layout::Topology)
+}
+
+/// Parse a report file whose stations and assignments are declared in
+/// `topology`, typically loaded with `parse_topology`. The file needs no
+/// `[Stations]` section; any it has are returned as usual. Reports and
+/// windows must reference chargers and stations from `topology` or from the
+/// file itself.
+pub fn parse_reports_with(
+    input: &str,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+) -> Result<ParsedInput, ParseError> {
+    parse_reports_reader(input.as_bytes(), topology, options)
+}
+
+/// Like `parse_reports_with`, reading line by line from `reader`.
+pub fn parse_reports_reader(
+    reader: impl BufRead,
+    topology: &ParsedInput,
+    options: &ParseOptions,
+) -> Result<ParsedInput, ParseError> {
+    parse_versioned(reader, options, Layout::Reports(topology))
+}
+
+/// Which sections a file must provide.
+#[derive(Debug, Clone, Copy)]
+enum Layout<'a> {
+    /// Stations and reports in the same file.
+    Complete,
+    /// Stations only; reports are optional.
+    Topology,
+    /// Reports against stations declared elsewhere.
+    Reports(&'a ParsedInput),
+}
+
+fn parse_versioned(
+    reader: impl BufRead,
+    options: &ParseOptions,
+    layout: Layout<'_>,
+) -> Result<ParsedInput, ParseError> {
+    // Directives come first, so the version is known once the leading
+    // comment and directive lines have been buffered.
+    let mut lines = reader.lines();
+    let mut head: Vec<String> = Vec::new();
+    for line in lines.by_ref() {
+        let line = line?;
+        let content = strip_comment(&line).trim();
+        let leading = content.is_empty() || content.starts_with('@');
+        head.push(line);
+        if !leading {
+            break;
+        }
+    }
+    let version = declared_format_version(&head)?;
+    let lines = head.into_iter().map(Ok).chain(lines);
+    match version.unwrap_or(1) {
+        0 => Err(ParseError::InvalidFormat(
+            "format version must be at least 1".into(),
+        )),
+        1 => parse_v1(lines, options, layout),
+        found => Err(ParseError::UnsupportedVersion {
+            found,
+            supported: CURRENT_FORMAT_VERSION,
+        }),
+    }
+}
+
+/// Find the `@format_version` directive among the leading comment and
+/// directive lines, without parsing the rest of the file.
+fn declared_format_version(head: &[String]) -> Result<Option<u32>, ParseError> {
+    for (line_idx, raw_line) in head.iter().enumerate() {
+        let line = strip_comment(raw_line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(directive) = line.strip_prefix('@') else {
+            break;
+        };
+        if let Some(("format_version", value)) = directive.split_once(char::is_whitespace) {
+            return value.trim().parse().map(Some).map_err(|_| {
+                ParseError::InvalidFormat(format!(
+                    "invalid format version at line {}",
+                    line_idx + 1
+                ))
+            });
+        }
+    }
+    Ok(None)
+}
+
+/// Version 1: the bracketed sections described on `parse_document`.
+fn parse_v1(
+    lines: impl Iterator<Item = std::io::Result<String>>,
+    options: &ParseOptions,
+    layout: Layout<'_>,
+) -> Result<ParsedInput, ParseError> {
     enum Section {
         None,
         Stations,
         Reports,
+        Maintenance,
+        Outages,
+        Assignments,
+        Metadata,
     }
 
     let mut section = Section::None;
     let mut saw_stations = false;
     let mut saw_reports = false;
     let mut stations: Vec<Station> = Vec::new();
-    let mut reports: Vec<ChargerReport> = Vec::new();
+    let mut station_lines: BTreeMap<StationId, usize> = BTreeMap::new();
+    // Records that reference stations or chargers are kept with their line
+    // numbers and checked once the whole file is read, so sections may come
+    // in any order and may repeat.
+    let mut pending_reports: Vec<(usize, ChargerReport)> = Vec::new();
+    let mut pending_maintenance: Vec<(usize, MaintenanceWindow)> = Vec::new();
+    let mut pending_outages: Vec<(usize, OutageWindow)> = Vec::new();
+    let mut pending_assignments: Vec<(usize, ChargerAssignment)> = Vec::new();
+    let mut pending_metadata: Vec<(usize, StationId, String, String)> = Vec::new();
+    // Station lines without chargers, valid only if an assignment names them
+    let mut empty_stations: Vec<(usize, StationId)> = Vec::new();
+    let mut warnings: Vec<ParseWarning> = Vec::new();
+    let mut header = InputHeader::default();
     // Track data hygiene constraints while parsing
     let mut seen_station_ids: std::collections::BTreeSet<u32> = std::collections::BTreeSet::new();
     let mut seen_charger_ids: std::collections::BTreeSet<u32> = std::collections::BTreeSet::new();
     let mut known_chargers: std::collections::BTreeSet<u32> = std::collections::BTreeSet::new();
+    let mut known_stations: std::collections::BTreeSet<u32> = std::collections::BTreeSet::new();
+    // Chargers with a fixed station, which therefore cannot have assignments
+    let mut static_chargers: std::collections::BTreeSet<u32> = std::collections::BTreeSet::new();
+    if let Layout::Reports(topology) = layout {
+        for station in &topology.stations {
+            known_stations.insert(station.id.0);
+            static_chargers.extend(station.chargers.iter().map(|c| c.0));
+        }
+        known_chargers.extend(topology.known_chargers().iter().map(|c| c.0));
+    }
 
-    for (line_idx, raw_line) in input.lines().enumerate() {
-        let line = raw_line.trim();
+    for (line_idx, raw_line) in lines.enumerate() {
+        let raw_line = raw_line?;
+        let line = strip_comment(&raw_line).trim();
         if line.is_empty() {
             continue;
         }
 
-        if line == "[Stations]" {
-            if saw_stations {
-                return Err(ParseError::InvalidFormat(
-                    "duplicate [Stations] header".into(),
-                ));
+        if let Some(directive) = line.strip_prefix('@') {
+            if !matches!(section, Section::None) {
+                return Err(ParseError::InvalidFormat(format!(
+                    "directive after first section header at line {}",
+                    line_idx + 1
+                )));
             }
+            apply_directive(&mut header, directive, line_idx + 1)?;
+            continue;
+        }
+
+        if line == "[Stations]" {
             section = Section::Stations;
             saw_stations = true;
             continue;
         }
         if line == "[Charger Availability Reports]" {
-            if saw_reports {
-                return Err(ParseError::InvalidFormat(
-                    "duplicate [Charger Availability Reports] header".into(),
-                ));
-            }
             section = Section::Reports;
             saw_reports = true;
             continue;
         }
+        if line == "[Maintenance Windows]" {
+            section = Section::Maintenance;
+            continue;
+        }
+        if line == "[Outages]" {
+            section = Section::Outages;
+            continue;
+        }
+        if line == "[Charger Assignments]" {
+            section = Section::Assignments;
+            continue;
+        }
+        if line == "[Station Metadata]" {
+            section = Section::Metadata;
+            continue;
+        }
 
         match section {
             Section::Stations => {
@@ -75,6 +343,7 @@ pub fn parse_input(input: &str) -> Result<(Vec<Station>, Vec<ChargerReport>), Pa
                             line_idx + 1
                         ))
                     })?;
+                known_stations.insert(station_id);
                 if !seen_station_ids.insert(station_id) {
                     return Err(ParseError::InvalidFormat(format!(
                         "duplicate station id {} detected (line {})",
@@ -107,72 +376,68 @@ pub fn parse_input(input: &str) -> Result<(Vec<Station>, Vec<ChargerReport>), Pa
                         )));
                     }
                     known_chargers.insert(cid);
+                    static_chargers.insert(cid);
                     chargers.push(ChargerId(cid));
                 }
                 if chargers.is_empty() {
-                    return Err(ParseError::InvalidFormat(format!(
-                        "station {} has no chargers (line {})",
-                        station_id,
-                        line_idx + 1
-                    )));
+                    empty_stations.push((line_idx + 1, StationId(station_id)));
                 }
+                station_lines.insert(StationId(station_id), line_idx + 1);
                 stations.push(Station {
                     id: StationId(station_id),
                     chargers,
                 });
             }
-            Section::Reports => {
+            Section::Reports => match parse_report(line, line_idx + 1) {
+                Ok(report) => pending_reports.push((line_idx + 1, report)),
+                Err(e) if options.lenient => warnings.push(ParseWarning {
+                    line: line_idx + 1,
+                    message: e.to_string(),
+                }),
+                Err(e) => return Err(e),
+            },
+            Section::Maintenance => {
                 let tokens: Vec<&str> = line.split_whitespace().collect();
                 if tokens.len() != 4 {
                     return Err(ParseError::InvalidFormat(format!(
-                        "invalid report format at line {}",
+                        "invalid maintenance window format at line {}",
                         line_idx + 1
                     )));
                 }
-                let charger: u32 = tokens[0].parse().map_err(|_| {
-                    ParseError::InvalidFormat(format!(
-                        "invalid charger id at line {}",
-                        line_idx + 1
-                    ))
-                })?;
-                if !known_chargers.contains(&charger) {
+                let (target, interval) = parse_window(&tokens, "maintenance window", line_idx + 1)?;
+                pending_maintenance.push((line_idx + 1, MaintenanceWindow { target, interval }));
+            }
+            Section::Outages => {
+                let tokens: Vec<&str> = line.split_whitespace().collect();
+                if tokens.len() != 5 {
                     return Err(ParseError::InvalidFormat(format!(
-                        "report references unknown charger id {} (line {})",
-                        charger,
+                        "invalid outage format at line {}",
                         line_idx + 1
                     )));
                 }
-                let start: u64 = tokens[1].parse().map_err(|_| {
+                let (target, interval) = parse_window(&tokens, "outage", line_idx + 1)?;
+                let category: OutageCategory = tokens[4].parse().map_err(|_| {
                     ParseError::InvalidFormat(format!(
-                        "invalid start time at line {}",
+                        "invalid outage category at line {}",
                         line_idx + 1
                     ))
                 })?;
-                let end: u64 = tokens[2].parse().map_err(|_| {
-                    ParseError::InvalidFormat(format!("invalid end time at line {}", line_idx + 1))
-                })?;
-                if end <= start {
-                    return Err(ParseError::InvalidFormat(format!(
-                        "end must be > start at line {}",
-                        line_idx + 1
-                    )));
-                }
-                let up = match tokens[3] {
-                    "true" => true,
-                    "false" => false,
-                    _ => {
-                        return Err(ParseError::InvalidFormat(format!(
-                            "invalid up flag at line {}",
-                            line_idx + 1
-                        )));
-                    }
-                };
-
-                reports.push(ChargerReport {
-                    charger: ChargerId(charger),
-                    interval: Interval { start, end },
-                    up,
-                });
+                pending_outages.push((
+                    line_idx + 1,
+                    OutageWindow {
+                        target,
+                        interval,
+                        category,
+                    },
+                ));
+            }
+            Section::Assignments => {
+                let assignment = parse_assignment(line, line_idx + 1)?;
+                pending_assignments.push((line_idx + 1, assignment));
+            }
+            Section::Metadata => {
+                let (station, key, value) = parse_metadata(line, line_idx + 1)?;
+                pending_metadata.push((line_idx + 1, station, key, value));
             }
             Section::None => {
                 return Err(ParseError::InvalidFormat(format!(
@@ -183,24 +448,365 @@ pub fn parse_input(input: &str) -> Result<(Vec<Station>, Vec<ChargerReport>), Pa
         }
     }
 
-    if !saw_stations || !saw_reports {
+    let complete = match layout {
+        Layout::Complete => saw_stations && saw_reports,
+        Layout::Topology => saw_stations,
+        Layout::Reports(_) => saw_reports,
+    };
+    if !complete {
         return Err(ParseError::InvalidFormat(
             "missing required sections".into(),
         ));
     }
-    if reports.is_empty() {
+
+    let mut assignments = Vec::with_capacity(pending_assignments.len());
+    let mut assignment_lines = Vec::with_capacity(pending_assignments.len());
+    for (line_no, assignment) in &pending_assignments {
+        if !known_stations.contains(&assignment.station.0) {
+            return Err(ParseError::InvalidFormat(format!(
+                "charger assignment references unknown station id {} (line {})",
+                assignment.station.0, line_no
+            )));
+        }
+        if static_chargers.contains(&assignment.charger.0) {
+            return Err(ParseError::InvalidFormat(format!(
+                "charger {} is listed in [Stations] and also has assignments (line {})",
+                assignment.charger.0, line_no
+            )));
+        }
+        known_chargers.insert(assignment.charger.0);
+        assignments.push(assignment.clone());
+        assignment_lines.push(*line_no);
+    }
+    // A charger can only be at one station at a time
+    pending_assignments.sort_by_key(|(_, a)| (a.charger, a.interval.start));
+    for pair in pending_assignments.windows(2) {
+        let ((_, a), (line_no, b)) = (&pair[0], &pair[1]);
+        if a.charger == b.charger && b.interval.start < a.interval.end {
+            return Err(ParseError::InvalidFormat(format!(
+                "charger {} has overlapping assignments (line {})",
+                b.charger.0, line_no
+            )));
+        }
+    }
+    for (line_no, station) in empty_stations {
+        if !assignments.iter().any(|a| a.station == station) {
+            return Err(ParseError::InvalidFormat(format!(
+                "station {} has no chargers (line {})",
+                station.0, line_no
+            )));
+        }
+    }
+
+    let mut reports = Vec::with_capacity(pending_reports.len());
+    for (line_no, report) in pending_reports {
+        if known_chargers.contains(&report.charger.0) {
+            reports.push(report);
+            continue;
+        }
+        let message = format!(
+            "report references unknown charger id {} (line {})",
+            report.charger.0, line_no
+        );
+        if !options.lenient {
+            return Err(ParseError::InvalidFormat(message));
+        }
+        warnings.push(ParseWarning {
+            line: line_no,
+            message,
+        });
+    }
+    warnings.sort_by_key(|w| w.line);
+    if reports.is_empty() && saw_reports {
         return Err(ParseError::InvalidFormat(
             "no charger availability reports found".into(),
         ));
     }
 
-    Ok((stations, reports))
+    let mut maintenance = Vec::with_capacity(pending_maintenance.len());
+    for (line_no, window) in pending_maintenance {
+        check_target(
+            window.target,
+            "maintenance window",
+            line_no,
+            &known_stations,
+            &known_chargers,
+        )?;
+        maintenance.push(window);
+    }
+    let mut outages = Vec::with_capacity(pending_outages.len());
+    for (line_no, outage) in pending_outages {
+        check_target(
+            outage.target,
+            "outage",
+            line_no,
+            &known_stations,
+            &known_chargers,
+        )?;
+        outages.push(outage);
+    }
+
+    let mut metadata: BTreeMap<StationId, StationMetadata> = BTreeMap::new();
+    for (line_no, station, key, value) in pending_metadata {
+        if !known_stations.contains(&station.0) {
+            return Err(ParseError::InvalidFormat(format!(
+                "station metadata references unknown station id {} (line {})",
+                station.0, line_no
+            )));
+        }
+        if !metadata.entry(station).or_default().set(&key, value) {
+            return Err(ParseError::InvalidFormat(format!(
+                "duplicate metadata key '{}' for station {} (line {})",
+                key, station.0, line_no
+            )));
+        }
+    }
+
+    Ok(ParsedInput {
+        header,
+        stations,
+        station_lines,
+        metadata,
+        assignments,
+        assignment_lines,
+        reports,
+        maintenance,
+        outages,
+        warnings,
+    })
+}
+
+/// Drop a `#` comment: either the whole line or a trailing comment preceded by
+/// whitespace.
+fn strip_comment(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    let cut = bytes
+        .iter()
+        .enumerate()
+        .position(|(i, &b)| b == b'#' && (i == 0 || bytes[i - 1].is_ascii_whitespace()));
+    match cut {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+/// Record one `@key value` directive (without the `@`) in `header`.
+fn apply_directive(
+    header: &mut InputHeader,
+    directive: &str,
+    line_no: usize,
+) -> Result<(), ParseError> {
+    let (key, value) = match directive.split_once(char::is_whitespace) {
+        Some((key, value)) if !key.is_empty() && !value.trim().is_empty() => (key, value.trim()),
+        _ => {
+            return Err(ParseError::InvalidFormat(format!(
+                "directive needs a key and a value at line {line_no}"
+            )));
+        }
+    };
+    let duplicate =
+        || ParseError::InvalidFormat(format!("duplicate @{key} directive at line {line_no}"));
+    match key {
+        "source" => {
+            if header.source.replace(value.to_string()).is_some() {
+                return Err(duplicate());
+            }
+        }
+        "exported_at" => {
+            if header.exported_at.replace(value.to_string()).is_some() {
+                return Err(duplicate());
+            }
+        }
+        "time_unit" => {
+            let unit: TimeUnit = value.parse().map_err(|_| {
+                ParseError::InvalidFormat(format!("invalid time unit at line {line_no}"))
+            })?;
+            if header.time_unit.replace(unit).is_some() {
+                return Err(duplicate());
+            }
+        }
+        "format_version" => {
+            let version: u32 = value.parse().map_err(|_| {
+                ParseError::InvalidFormat(format!("invalid format version at line {line_no}"))
+            })?;
+            if header.format_version.replace(version).is_some() {
+                return Err(duplicate());
+            }
+        }
+        _ => {
+            if header
+                .extra
+                .insert(key.to_string(), value.to_string())
+                .is_some()
+            {
+                return Err(duplicate());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse one `<charger_id>[:<connector_id>] <start> <end> <status>` report line.
+fn parse_report(line: &str, line_no: usize) -> Result<ChargerReport, ParseError> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let [unit, start, end, status] = tokens[..] else {
+        return Err(ParseError::InvalidFormat(format!(
+            "invalid report format at line {line_no}"
+        )));
+    };
+    report_from_fields(unit, None, start, end, status)
+        .map_err(|message| ParseError::InvalidFormat(format!("{message} at line {line_no}")))
+}
+
+/// Check the fields of one report, however the input format lays them out,
+/// and build the report. `unit` is a `<charger_id>[:<connector_id>]` token;
+/// formats with a separate connector field pass it as `connector`. Errors
+/// name the bad field; callers add the line.
+pub(crate) fn report_from_fields(
+    unit: &str,
+    connector: Option<&str>,
+    start: &str,
+    end: &str,
+    status: &str,
+) -> Result<ChargerReport, String> {
+    let unit: ChargerToken = unit.parse().map_err(|_| "invalid charger id".to_string())?;
+    let connector = match (unit.connector, connector) {
+        (connector, None) => connector,
+        (None, Some(value)) => Some(ConnectorId(
+            value
+                .parse()
+                .map_err(|_| "invalid connector id".to_string())?,
+        )),
+        (Some(_), Some(_)) => return Err("connector given twice".into()),
+    };
+    let start: u64 = start
+        .parse()
+        .map_err(|_| "invalid start time".to_string())?;
+    let end: u64 = end.parse().map_err(|_| "invalid end time".to_string())?;
+    if end <= start {
+        return Err("end must be > start".into());
+    }
+    let status: ChargerStatus = status.parse().map_err(|_| "invalid status".to_string())?;
+    Ok(ChargerReport {
+        charger: unit.charger,
+        connector,
+        interval: Interval { start, end },
+        status,
+    })
+}
+
+/// Parse one `<station_id> <key> <value>` metadata line; the value is the
+/// rest of the line and may contain spaces.
+fn parse_metadata(line: &str, line_no: usize) -> Result<(StationId, String, String), ParseError> {
+    let invalid =
+        || ParseError::InvalidFormat(format!("invalid station metadata format at line {line_no}"));
+    let (station, rest) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let (key, value) = rest
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    let station: u32 = station
+        .parse()
+        .map_err(|_| ParseError::InvalidFormat(format!("invalid station id at line {line_no}")))?;
+    let value = value.trim();
+    StationMetadata::check(key, value)
+        .map_err(|message| ParseError::InvalidFormat(format!("{message} at line {line_no}")))?;
+    Ok((StationId(station), key.to_string(), value.to_string()))
+}
+
+/// Parse one `<charger_id> <station_id> <from> [<until>]` assignment line.
+fn parse_assignment(line: &str, line_no: usize) -> Result<ChargerAssignment, ParseError> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if !(3..=4).contains(&tokens.len()) {
+        return Err(ParseError::InvalidFormat(format!(
+            "invalid charger assignment format at line {line_no}"
+        )));
+    }
+    let charger: u32 = tokens[0]
+        .parse()
+        .map_err(|_| ParseError::InvalidFormat(format!("invalid charger id at line {line_no}")))?;
+    let station: u32 = tokens[1]
+        .parse()
+        .map_err(|_| ParseError::InvalidFormat(format!("invalid station id at line {line_no}")))?;
+    let start: u64 = tokens[2]
+        .parse()
+        .map_err(|_| ParseError::InvalidFormat(format!("invalid start time at line {line_no}")))?;
+    let end: u64 = match tokens.get(3) {
+        None => u64::MAX,
+        Some(token) => token.parse().map_err(|_| {
+            ParseError::InvalidFormat(format!("invalid end time at line {line_no}"))
+        })?,
+    };
+    if end <= start {
+        return Err(ParseError::InvalidFormat(format!(
+            "end must be > start at line {line_no}"
+        )));
+    }
+    Ok(ChargerAssignment {
+        charger: ChargerId(charger),
+        station: StationId(station),
+        interval: Interval { start, end },
+    })
+}
+
+/// Parse the leading `<station|charger> <id> <start> <end>` tokens shared by
+/// maintenance and outage lines.
+fn parse_window(
+    tokens: &[&str],
+    what: &str,
+    line_no: usize,
+) -> Result<(WindowTarget, Interval), ParseError> {
+    let id: u32 = tokens[1].parse().map_err(|_| {
+        ParseError::InvalidFormat(format!("invalid {} id at line {}", tokens[0], line_no))
+    })?;
+    let target = match tokens[0] {
+        "station" => WindowTarget::Station(StationId(id)),
+        "charger" => WindowTarget::Charger(ChargerId(id)),
+        _ => {
+            return Err(ParseError::InvalidFormat(format!(
+                "{} target must be 'station' or 'charger' at line {}",
+                what, line_no
+            )));
+        }
+    };
+    let start: u64 = tokens[2]
+        .parse()
+        .map_err(|_| ParseError::InvalidFormat(format!("invalid start time at line {line_no}")))?;
+    let end: u64 = tokens[3]
+        .parse()
+        .map_err(|_| ParseError::InvalidFormat(format!("invalid end time at line {line_no}")))?;
+    if end <= start {
+        return Err(ParseError::InvalidFormat(format!(
+            "end must be > start at line {line_no}"
+        )));
+    }
+    Ok((target, Interval { start, end }))
+}
+
+/// Check that a maintenance or outage target was declared in `[Stations]`.
+fn check_target(
+    target: WindowTarget,
+    what: &str,
+    line_no: usize,
+    station_ids: &std::collections::BTreeSet<u32>,
+    charger_ids: &std::collections::BTreeSet<u32>,
+) -> Result<(), ParseError> {
+    let (kind, id, known) = match target {
+        WindowTarget::Station(id) => ("station", id.0, station_ids.contains(&id.0)),
+        WindowTarget::Charger(id) => ("charger", id.0, charger_ids.contains(&id.0)),
+    };
+    if known {
+        return Ok(());
+    }
+    Err(ParseError::InvalidFormat(format!(
+        "{what} references unknown {kind} id {id} (line {line_no})"
+    )))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{ChargerId, Interval, StationId};
+    use crate::types::{ChargerId, ConnectorId, Interval, StationId};
 
     #[test]
     fn parse_valid_input_minimal() {
@@ -212,7 +818,7 @@ mod tests {
         assert_eq!(reports.len(), 1);
         assert_eq!(reports[0].charger, ChargerId(100));
         assert_eq!(reports[0].interval, Interval { start: 0, end: 100 });
-        assert!(reports[0].up);
+        assert_eq!(reports[0].status, ChargerStatus::Up);
     }
 
     #[test]
@@ -252,8 +858,259 @@ mod tests {
     }
 
     #[test]
-    fn duplicate_header_rejected() {
-        let input = "[Stations]\n1 100\n[Stations]\n2 200\n\n[Charger Availability Reports]\n100 0 10 true\n";
+    fn parse_named_statuses() {
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 charging\n100 10 20 maintenance\n";
+        let (_, reports) = parse_input(input).unwrap();
+        assert_eq!(reports[0].status, ChargerStatus::Charging);
+        assert_eq!(reports[1].status, ChargerStatus::Maintenance);
+
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 sleeping\n";
         assert!(parse_input(input).is_err());
     }
+
+    #[test]
+    fn parse_maintenance_windows() {
+        let input = "[Stations]\n1 100 101\n\n[Charger Availability Reports]\n100 0 10 true\n\n[Maintenance Windows]\nstation 1 0 5\ncharger 101 5 10\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(
+            doc.maintenance,
+            vec![
+                MaintenanceWindow {
+                    target: WindowTarget::Station(StationId(1)),
+                    interval: Interval { start: 0, end: 5 },
+                },
+                MaintenanceWindow {
+                    target: WindowTarget::Charger(ChargerId(101)),
+                    interval: Interval { start: 5, end: 10 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn maintenance_window_for_unknown_target_rejected() {
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n\n[Maintenance Windows]\nstation 2 0 5\n";
+        assert!(parse_document(input).is_err());
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n\n[Maintenance Windows]\nsite 1 0 5\n";
+        assert!(parse_document(input).is_err());
+    }
+
+    #[test]
+    fn parse_outages_with_categories() {
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 false\n\n[Outages]\nstation 1 0 5 grid_outage\ncharger 100 5 10 vandalism\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(doc.outages.len(), 2);
+        assert_eq!(doc.outages[0].category, OutageCategory::GridOutage);
+        assert_eq!(doc.outages[1].target, WindowTarget::Charger(ChargerId(100)));
+
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 false\n\n[Outages]\nstation 1 0 5 aliens\n";
+        assert!(parse_document(input).is_err());
+    }
+
+    #[test]
+    fn lenient_mode_skips_bad_report_lines() {
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n999 0 10 true\n100 10 5 true\n100 10 20 maybe\n100 10\n100 20 30 false\n";
+        assert!(parse_document(input).is_err());
+
+        let options = ParseOptions { lenient: true };
+        let doc = parse_document_with(input, &options).unwrap();
+        assert_eq!(doc.reports.len(), 2);
+        let lines: Vec<usize> = doc.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![6, 7, 8, 9]);
+        assert!(doc.warnings[0].message.contains("unknown charger id 999"));
+    }
+
+    #[test]
+    fn lenient_mode_keeps_structural_errors_fatal() {
+        let options = ParseOptions { lenient: true };
+        let input = "[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_document_with(input, &options).is_err());
+        let input = "[Stations]\n1 abc\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_document_with(input, &options).is_err());
+    }
+
+    #[test]
+    fn comments_are_ignored() {
+        let input = "# fixture for station 1\n[Stations]\n1 100 # main site\n\n[Charger Availability Reports]\n  # morning\n100 0 10 true\t# ok\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(doc.stations[0].chargers, vec![ChargerId(100)]);
+        assert_eq!(doc.reports.len(), 1);
+    }
+
+    #[test]
+    fn directives_fill_the_header() {
+        let input = "@source warehouse-eu\n@exported_at 2024-05-01T00:00:00Z\n@time_unit ms\n@format_version 1\n@region west\n[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        let header = parse_document(input).unwrap().header;
+        assert_eq!(header.source.as_deref(), Some("warehouse-eu"));
+        assert_eq!(header.exported_at.as_deref(), Some("2024-05-01T00:00:00Z"));
+        assert_eq!(header.time_unit, Some(TimeUnit::Milliseconds));
+        assert_eq!(header.format_version, Some(1));
+        assert_eq!(header.extra["region"], "west");
+    }
+
+    #[test]
+    fn misplaced_or_malformed_directives_rejected() {
+        let input =
+            "[Stations]\n@source late\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_document(input).is_err());
+        let input = "@source\n[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_document(input).is_err());
+        let input = "@time_unit fortnights\n[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_document(input).is_err());
+        let input = "@source a\n@source b\n[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_document(input).is_err());
+    }
+
+    #[test]
+    fn format_version_dispatch() {
+        let body = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_document(body).is_ok());
+        assert!(parse_document(&format!("# archived\n@format_version 1\n{body}")).is_ok());
+        assert!(matches!(
+            parse_document(&format!("@source x\n@format_version 2\n{body}")),
+            Err(ParseError::UnsupportedVersion {
+                found: 2,
+                supported: CURRENT_FORMAT_VERSION
+            })
+        ));
+        assert!(parse_document(&format!("@format_version 0\n{body}")).is_err());
+        assert!(parse_document(&format!("@format_version v1\n{body}")).is_err());
+    }
+
+    #[test]
+    fn repeated_sections_are_merged() {
+        let input = "[Stations]\n1 100\n[Charger Availability Reports]\n100 0 10 true\n[Stations]\n2 200\n\n[Charger Availability Reports]\n200 0 10 false\n";
+        let (stations, reports) = parse_input(input).unwrap();
+        assert_eq!(stations.len(), 2);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[1].charger, ChargerId(200));
+
+        // A station id repeated across sections is still a duplicate
+        let input = "[Stations]\n1 100\n[Stations]\n1 200\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        assert!(parse_input(input).is_err());
+    }
+
+    #[test]
+    fn sections_in_any_order() {
+        let input = "[Outages]\ncharger 100 0 5 grid_outage\n[Charger Availability Reports]\n100 0 10 true\n[Maintenance Windows]\nstation 1 5 10\n[Stations]\n1 100\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(doc.stations.len(), 1);
+        assert_eq!(doc.reports.len(), 1);
+        assert_eq!(doc.maintenance.len(), 1);
+        assert_eq!(doc.outages.len(), 1);
+
+        // References are still checked against the whole file
+        let input =
+            "[Charger Availability Reports]\n100 0 10 true\n101 0 10 true\n[Stations]\n1 100\n";
+        let err = parse_document(input).unwrap_err();
+        assert!(err.to_string().contains("unknown charger id 101 (line 3)"));
+    }
+
+    #[test]
+    fn reports_against_separate_topology() {
+        let topology = parse_topology("@source asset-db\n[Stations]\n1 100 101\n2 200\n").unwrap();
+        assert_eq!(topology.stations.len(), 2);
+        assert!(topology.reports.is_empty());
+        assert!(parse_topology("[Charger Availability Reports]\n100 0 10 true\n").is_err());
+
+        let options = ParseOptions::default();
+        let input = "[Charger Availability Reports]\n100 0 10 true\n200 0 10 false\n[Outages]\nstation 2 0 5 vandalism\n";
+        let doc = parse_reports_with(input, &topology, &options).unwrap();
+        assert!(doc.stations.is_empty());
+        assert_eq!(doc.reports.len(), 2);
+        assert_eq!(doc.outages.len(), 1);
+
+        let input = "[Charger Availability Reports]\n100 0 10 true\n300 0 10 true\n";
+        let err = parse_reports_with(input, &topology, &options).unwrap_err();
+        assert!(err.to_string().contains("unknown charger id 300 (line 3)"));
+        assert!(parse_reports_with("[Stations]\n3 300\n", &topology, &options).is_err());
+    }
+
+    #[test]
+    fn reports_may_name_a_connector() {
+        let input =
+            "[Stations]\n1 100\n[Charger Availability Reports]\n100:2 0 10 true\n100 10 20 false\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(doc.reports[0].connector, Some(ConnectorId(2)));
+        assert_eq!(doc.reports[0].token().to_string(), "100:2");
+        assert_eq!(doc.reports[1].connector, None);
+
+        let err =
+            parse_document("[Stations]\n1 100\n[Charger Availability Reports]\n100:x 0 10 true\n")
+                .unwrap_err();
+        assert!(err.to_string().contains("invalid charger id at line 4"));
+    }
+
+    #[test]
+    fn report_fields_take_the_connector_from_either_place() {
+        let report = report_from_fields("100", Some("2"), "0", "10", "up").unwrap();
+        assert_eq!(report.token().to_string(), "100:2");
+        let report = report_from_fields("100:3", None, "0", "10", "up").unwrap();
+        assert_eq!(report.connector, Some(ConnectorId(3)));
+        let err = report_from_fields("100:3", Some("2"), "0", "10", "up").unwrap_err();
+        assert_eq!(err, "connector given twice");
+        let err = report_from_fields("100", None, "10", "10", "up").unwrap_err();
+        assert_eq!(err, "end must be > start");
+    }
+
+    #[test]
+    fn metadata_must_name_known_stations_once() {
+        let reports = "[Charger Availability Reports]\n100 0 10 true\n";
+        let doc = parse_document(&format!(
+            "[Stations]\n1 100\n[Station Metadata]\n1 region  north west  # comment\n{reports}"
+        ))
+        .unwrap();
+        assert_eq!(
+            doc.metadata[&StationId(1)].region.as_deref(),
+            Some("north west")
+        );
+
+        let err = parse_document(&format!(
+            "[Stations]\n1 100\n[Station Metadata]\n2 name Depot\n{reports}"
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown station id 2 (line 4)"));
+        let err = parse_document(&format!(
+            "[Stations]\n1 100\n[Station Metadata]\n1 name A\n1 name B\n{reports}"
+        ))
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("duplicate metadata key 'name' for station 1 (line 5)")
+        );
+        let err = parse_document(&format!(
+            "[Stations]\n1 100\n[Station Metadata]\n1 name\n{reports}"
+        ))
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("invalid station metadata format at line 4")
+        );
+    }
+
+    #[test]
+    fn parse_charger_assignments() {
+        let input = "[Stations]\n1 100\n2\n[Charger Assignments]\n200 1 0 50\n200 2 50\n[Charger Availability Reports]\n200 0 100 true\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(doc.stations[1].chargers, vec![]);
+        assert_eq!(doc.assignments.len(), 2);
+        assert_eq!(doc.assignments[1].interval.end, u64::MAX);
+        assert_eq!(doc.reports.len(), 1);
+        let known: Vec<_> = doc.known_chargers().into_iter().collect();
+        assert_eq!(known, vec![ChargerId(100), ChargerId(200)]);
+
+        let bad = [
+            // overlapping assignments
+            "[Stations]\n1 100\n2 101\n[Charger Assignments]\n200 1 0 50\n200 2 40\n[Charger Availability Reports]\n200 0 100 true\n",
+            // charger with a fixed station
+            "[Stations]\n1 100\n2 101\n[Charger Assignments]\n100 2 0\n[Charger Availability Reports]\n100 0 100 true\n",
+            // unknown station
+            "[Stations]\n1 100\n[Charger Assignments]\n200 3 0\n[Charger Availability Reports]\n200 0 100 true\n",
+            // station without chargers or assignments
+            "[Stations]\n1 100\n2\n[Charger Availability Reports]\n100 0 100 true\n",
+        ];
+        for input in bad {
+            assert!(parse_document(input).is_err(), "{input}");
+        }
+    }
 }
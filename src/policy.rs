@@ -0,0 +1,183 @@
+use crate::types::{ChargerStatus, OutageCategory};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PolicyError {
+    #[error("invalid classification '{0}', expected <status>=<class>")]
+    InvalidSpec(String),
+    #[error("unknown status class '{0}', expected available|unavailable|excluded")]
+    UnknownClass(String),
+    #[error(transparent)]
+    UnknownStatus(#[from] crate::types::UnknownStatus),
+    #[error(transparent)]
+    UnknownCategory(#[from] crate::types::UnknownCategory),
+}
+
+/// How time spent in a given status counts towards uptime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusClass {
+    /// Counts towards both the numerator and the denominator.
+    Available,
+    /// Counts towards the denominator only.
+    Unavailable,
+    /// Removed from the denominator entirely.
+    Excluded,
+}
+
+impl FromStr for StatusClass {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "available" => Ok(StatusClass::Available),
+            "unavailable" => Ok(StatusClass::Unavailable),
+            "excluded" => Ok(StatusClass::Excluded),
+            _ => Err(PolicyError::UnknownClass(s.to_string())),
+        }
+    }
+}
+
+/// Classification of every `ChargerStatus` into a `StatusClass`.
+///
+/// The default keeps the original boolean semantics (`up` is available, `down`
+/// is unavailable) and treats planned maintenance as excluded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusPolicy {
+    classes: BTreeMap<ChargerStatus, StatusClass>,
+}
+
+impl Default for StatusPolicy {
+    fn default() -> Self {
+        let classes = ChargerStatus::ALL
+            .into_iter()
+            .map(|status| {
+                let class = match status {
+                    ChargerStatus::Up | ChargerStatus::Charging | ChargerStatus::Idle => {
+                        StatusClass::Available
+                    }
+                    ChargerStatus::Down | ChargerStatus::Faulted => StatusClass::Unavailable,
+                    ChargerStatus::Maintenance => StatusClass::Excluded,
+                };
+                (status, class)
+            })
+            .collect();
+        StatusPolicy { classes }
+    }
+}
+
+impl StatusPolicy {
+    pub fn classify(&self, status: ChargerStatus) -> StatusClass {
+        self.classes[&status]
+    }
+
+    pub fn set(&mut self, status: ChargerStatus, class: StatusClass) {
+        self.classes.insert(status, class);
+    }
+
+    /// Apply a `<status>=<class>` override, e.g. `maintenance=unavailable`.
+    pub fn apply_spec(&mut self, spec: &str) -> Result<(), PolicyError> {
+        let (status, class) = spec
+            .split_once('=')
+            .ok_or_else(|| PolicyError::InvalidSpec(spec.to_string()))?;
+        self.set(status.trim().parse()?, class.trim().parse()?);
+        Ok(())
+    }
+}
+
+/// Which outage categories are excused from the adjusted uptime.
+///
+/// By default grid outages, vandalism and force majeure are excused and
+/// `other` is not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcusePolicy {
+    excused: BTreeSet<OutageCategory>,
+}
+
+impl Default for ExcusePolicy {
+    fn default() -> Self {
+        ExcusePolicy {
+            excused: [
+                OutageCategory::GridOutage,
+                OutageCategory::Vandalism,
+                OutageCategory::ForceMajeure,
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+}
+
+impl ExcusePolicy {
+    /// A policy that excuses nothing, so adjusted uptime equals raw uptime.
+    pub fn none() -> Self {
+        ExcusePolicy {
+            excused: BTreeSet::new(),
+        }
+    }
+
+    pub fn is_excused(&self, category: OutageCategory) -> bool {
+        self.excused.contains(&category)
+    }
+
+    pub fn excuse(&mut self, category: OutageCategory) {
+        self.excused.insert(category);
+    }
+
+    /// Parse a comma-separated category list such as `grid_outage,vandalism`.
+    /// `none` yields an empty policy.
+    pub fn from_list(list: &str) -> Result<Self, PolicyError> {
+        let mut policy = ExcusePolicy::none();
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            if name != "none" {
+                policy.excuse(name.parse()?);
+            }
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_matches_boolean_semantics() {
+        let policy = StatusPolicy::default();
+        assert_eq!(policy.classify(ChargerStatus::Up), StatusClass::Available);
+        assert_eq!(
+            policy.classify(ChargerStatus::Down),
+            StatusClass::Unavailable
+        );
+        assert_eq!(
+            policy.classify(ChargerStatus::Maintenance),
+            StatusClass::Excluded
+        );
+    }
+
+    #[test]
+    fn spec_overrides_classification() {
+        let mut policy = StatusPolicy::default();
+        policy.apply_spec("maintenance=unavailable").unwrap();
+        assert_eq!(
+            policy.classify(ChargerStatus::Maintenance),
+            StatusClass::Unavailable
+        );
+        assert!(policy.apply_spec("maintenance").is_err());
+        assert!(policy.apply_spec("broken=available").is_err());
+        assert!(policy.apply_spec("idle=sometimes").is_err());
+    }
+
+    #[test]
+    fn excuse_list_parsing() {
+        let policy = ExcusePolicy::from_list("grid_outage, other").unwrap();
+        assert!(policy.is_excused(OutageCategory::GridOutage));
+        assert!(policy.is_excused(OutageCategory::Other));
+        assert!(!policy.is_excused(OutageCategory::Vandalism));
+        assert_eq!(
+            ExcusePolicy::from_list("none").unwrap(),
+            ExcusePolicy::none()
+        );
+        assert!(ExcusePolicy::from_list("meteor").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use crate::interval::merge_intervals;
+use crate::types::{ChargerAssignment, ChargerId, ChargerReport, Interval, Station, StationId};
+use std::collections::BTreeMap;
+
+/// One hour in nanoseconds, the default threshold for a suspicious gap.
+pub const DEFAULT_MAX_GAP: u64 = 3_600_000_000_000;
+
+/// Thresholds for the data-quality checks.
+#[derive(Debug, Clone)]
+pub struct QualityConfig {
+    /// Gaps between a charger's reports longer than this are flagged.
+    pub max_gap: u64,
+    /// Reports ending after this instant are flagged as in the future.
+    pub now: Option<u64>,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        QualityConfig {
+            max_gap: DEFAULT_MAX_GAP,
+            now: None,
+        }
+    }
+}
+
+/// Two reports for the same charger and connector whose intervals overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportOverlap {
+    pub first: ChargerReport,
+    pub second: ChargerReport,
+}
+
+/// A report that appears more than once with identical fields.
+/// Serialized as the report plus a `"count"` field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicateReport {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub report: ChargerReport,
+    pub count: usize,
+}
+
+/// Time between two of a charger's reports with no report covering it.
+/// Serialized as `{"charger": 1, "start": 10, "end": 20}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportGap {
+    pub charger: ChargerId,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub interval: Interval,
+}
+
+/// Problems in an input that parses but may not mean what it says.
+/// None of these findings stop uptime from being computed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QualityReport {
+    pub chargers_without_reports: Vec<ChargerId>,
+    /// Stations none of whose chargers reported, counting assigned chargers only
+    /// while assigned; these fail `compute_station_uptime`.
+    pub stations_without_reports: Vec<StationId>,
+    /// Overlapping reports that agree on the status.
+    pub overlapping_reports: Vec<ReportOverlap>,
+    /// Overlapping reports that disagree on the status.
+    pub conflicting_reports: Vec<ReportOverlap>,
+    pub duplicate_reports: Vec<DuplicateReport>,
+    /// Gaps between a charger's reports longer than `QualityConfig::max_gap`.
+    pub long_gaps: Vec<ReportGap>,
+    pub future_reports: Vec<ChargerReport>,
+}
+
+impl QualityReport {
+    pub fn is_clean(&self) -> bool {
+        *self == QualityReport::default()
+    }
+}
+
+/// Check `reports` against the stations' fixed chargers and the time-bounded
+/// `assignments`.
+pub fn assess_quality(
+    stations: &[Station],
+    assignments: &[ChargerAssignment],
+    reports: &[ChargerReport],
+    config: &QualityConfig,
+) -> QualityReport {
+    let mut quality = QualityReport::default();
+
+    let mut by_charger: BTreeMap<ChargerId, Vec<&ChargerReport>> = BTreeMap::new();
+    for r in reports {
+        by_charger.entry(r.charger).or_default().push(r);
+    }
+
+    let mut assigned: BTreeMap<StationId, Vec<&ChargerAssignment>> = BTreeMap::new();
+    for a in assignments {
+        assigned.entry(a.station).or_default().push(a);
+    }
+
+    for station in stations {
+        let mut any_reported = false;
+        for charger in &station.chargers {
+            if by_charger.contains_key(charger) {
+                any_reported = true;
+            } else {
+                quality.chargers_without_reports.push(*charger);
+            }
+        }
+        // An assigned charger's reports only count while it is assigned here
+        for a in assigned.get(&station.id).into_iter().flatten() {
+            match by_charger.get(&a.charger) {
+                Some(list) => {
+                    any_reported |= list.iter().any(|r| {
+                        r.interval.start < a.interval.end && a.interval.start < r.interval.end
+                    })
+                }
+                None => quality.chargers_without_reports.push(a.charger),
+            }
+        }
+        if !any_reported {
+            quality.stations_without_reports.push(station.id);
+        }
+    }
+    quality.chargers_without_reports.sort();
+    quality.chargers_without_reports.dedup();
+    quality.stations_without_reports.sort();
+
+    for (charger, list) in &mut by_charger {
+        // Connectors report independently, so only compare within one
+        list.sort_by_key(|r| (r.connector, r.interval.start, r.interval.end, r.status));
+
+        // Identical reports are adjacent after sorting; keep one of each for
+        // the overlap scan so duplicates are not reported twice.
+        let mut unique: Vec<&ChargerReport> = Vec::with_capacity(list.len());
+        for r in list.iter().copied() {
+            match unique.last() {
+                Some(last)
+                    if last.connector == r.connector
+                        && last.interval == r.interval
+                        && last.status == r.status =>
+                {
+                    match quality.duplicate_reports.last_mut() {
+                        Some(d) if d.report == *r => d.count += 1,
+                        _ => quality.duplicate_reports.push(DuplicateReport {
+                            report: r.clone(),
+                            count: 2,
+                        }),
+                    }
+                }
+                _ => unique.push(r),
+            }
+        }
+
+        for (i, a) in unique.iter().enumerate() {
+            for b in unique[i + 1..]
+                .iter()
+                .take_while(|b| b.connector == a.connector && b.interval.start < a.interval.end)
+            {
+                let overlap = ReportOverlap {
+                    first: (*a).clone(),
+                    second: (*b).clone(),
+                };
+                if a.status == b.status {
+                    quality.overlapping_reports.push(overlap);
+                } else {
+                    quality.conflicting_reports.push(overlap);
+                }
+            }
+        }
+
+        let mut intervals: Vec<Interval> = unique.iter().map(|r| r.interval).collect();
+        let merged = merge_intervals(&mut intervals);
+        for pair in merged.windows(2) {
+            if pair[1].start - pair[0].end > config.max_gap {
+                quality.long_gaps.push(ReportGap {
+                    charger: *charger,
+                    interval: Interval {
+                        start: pair[0].end,
+                        end: pair[1].start,
+                    },
+                });
+            }
+        }
+
+        if let Some(now) = config.now {
+            quality.future_reports.extend(
+                unique
+                    .iter()
+                    .filter(|r| r.interval.end > now)
+                    .map(|r| (*r).clone()),
+            );
+        }
+    }
+
+    quality
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChargerStatus;
+
+    fn report(charger: u32, start: u64, end: u64, status: ChargerStatus) -> ChargerReport {
+        ChargerReport {
+            charger: ChargerId(charger),
+            connector: None,
+            interval: Interval { start, end },
+            status,
+        }
+    }
+
+    #[test]
+    fn clean_input_has_no_findings() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10)],
+        }];
+        let reports = vec![
+            report(10, 0, 10, ChargerStatus::Up),
+            report(10, 10, 20, ChargerStatus::Down),
+        ];
+        let quality = assess_quality(&stations, &[], &reports, &QualityConfig::default());
+        assert!(quality.is_clean());
+    }
+
+    #[test]
+    fn missing_reports_are_listed() {
+        let stations = vec![
+            Station {
+                id: StationId(1),
+                chargers: vec![ChargerId(10), ChargerId(11)],
+            },
+            Station {
+                id: StationId(2),
+                chargers: vec![ChargerId(20)],
+            },
+        ];
+        let reports = vec![report(10, 0, 10, ChargerStatus::Up)];
+        let quality = assess_quality(&stations, &[], &reports, &QualityConfig::default());
+        assert_eq!(
+            quality.chargers_without_reports,
+            vec![ChargerId(11), ChargerId(20)]
+        );
+        assert_eq!(quality.stations_without_reports, vec![StationId(2)]);
+    }
+
+    #[test]
+    fn assigned_chargers_count_while_assigned() {
+        let stations = vec![
+            Station {
+                id: StationId(1),
+                chargers: vec![ChargerId(10)],
+            },
+            Station {
+                id: StationId(2),
+                chargers: vec![],
+            },
+            Station {
+                id: StationId(3),
+                chargers: vec![],
+            },
+        ];
+        let assign = |charger, station, start, end| ChargerAssignment {
+            charger: ChargerId(charger),
+            station: StationId(station),
+            interval: Interval { start, end },
+        };
+        let assignments = vec![
+            assign(20, 2, 0, 50),
+            assign(20, 3, 50, 100),
+            assign(30, 3, 0, 50),
+            assign(30, 2, 50, 100),
+        ];
+        // Charger 20 reports only while at station 2; charger 30 never reports
+        let reports = vec![
+            report(10, 0, 10, ChargerStatus::Up),
+            report(20, 0, 40, ChargerStatus::Up),
+        ];
+        let quality = assess_quality(&stations, &assignments, &reports, &QualityConfig::default());
+        assert_eq!(quality.chargers_without_reports, vec![ChargerId(30)]);
+        assert_eq!(quality.stations_without_reports, vec![StationId(3)]);
+    }
+
+    #[test]
+    fn duplicates_overlaps_and_conflicts_are_separated() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10)],
+        }];
+        let reports = vec![
+            report(10, 0, 10, ChargerStatus::Up),
+            report(10, 0, 10, ChargerStatus::Up),
+            report(10, 0, 10, ChargerStatus::Up),
+            report(10, 5, 15, ChargerStatus::Up),
+            report(10, 8, 12, ChargerStatus::Down),
+        ];
+        let quality = assess_quality(&stations, &[], &reports, &QualityConfig::default());
+        assert_eq!(quality.duplicate_reports.len(), 1);
+        assert_eq!(quality.duplicate_reports[0].count, 3);
+        assert_eq!(quality.overlapping_reports.len(), 1);
+        assert_eq!(quality.conflicting_reports.len(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn findings_serialize_as_reports() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10)],
+        }];
+        let reports = vec![
+            report(10, 0, 10, ChargerStatus::Up),
+            report(10, 0, 10, ChargerStatus::Up),
+            report(10, 5, 15, ChargerStatus::Down),
+        ];
+        let quality = assess_quality(&stations, &[], &reports, &QualityConfig::default());
+        let json = serde_json::to_value(&quality).unwrap();
+        assert_eq!(
+            json["duplicate_reports"][0],
+            serde_json::json!({"charger": 10, "start": 0, "end": 10, "status": "up", "count": 2})
+        );
+        assert_eq!(json["conflicting_reports"][0]["second"]["status"], "down");
+    }
+
+    #[test]
+    fn long_gaps_and_future_reports() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10)],
+        }];
+        let reports = vec![
+            report(10, 0, 10, ChargerStatus::Up),
+            report(10, 15, 20, ChargerStatus::Up),
+            report(10, 100, 200, ChargerStatus::Up),
+        ];
+        let config = QualityConfig {
+            max_gap: 10,
+            now: Some(150),
+        };
+        let quality = assess_quality(&stations, &[], &reports, &config);
+        assert_eq!(
+            quality.long_gaps,
+            vec![ReportGap {
+                charger: ChargerId(10),
+                interval: Interval {
+                    start: 20,
+                    end: 100
+                }
+            }]
+        );
+        assert_eq!(quality.future_reports.len(), 1);
+        assert_eq!(quality.future_reports[0].interval.start, 100);
+    }
+}
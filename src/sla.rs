@@ -0,0 +1,171 @@
+use crate::types::StationId;
+use crate::uptime::StationUptime;
+use std::collections::BTreeMap;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SlaError {
+    #[error("invalid SLA target '{0}', expected a percentage in [0, 100]")]
+    InvalidTarget(String),
+    #[error("invalid SLA config: {0}")]
+    InvalidConfig(String),
+}
+
+/// Uptime targets: an optional fleet-wide default plus per-station overrides.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SlaPolicy {
+    pub default_target: Option<f64>,
+    pub overrides: BTreeMap<StationId, f64>,
+}
+
+/// A station whose uptime fell below its target.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlaBreach {
+    pub station: StationId,
+    pub target: f64,
+    /// Unrounded uptime percentage.
+    pub actual: f64,
+}
+
+impl SlaPolicy {
+    pub fn target_for(&self, station: StationId) -> Option<f64> {
+        self.overrides
+            .get(&station)
+            .copied()
+            .or(self.default_target)
+    }
+
+    /// Stations below target, compared on the exact ratio rather than the
+    /// floored percentage so fractional targets such as 97.5 behave.
+    pub fn breaches(&self, results: &[StationUptime]) -> Vec<SlaBreach> {
+        results
+            .iter()
+            .filter_map(|r| {
+                let target = self.target_for(r.station)?;
+                let actual = r.available as f64 * 100.0 / r.reported as f64;
+                (actual < target).then_some(SlaBreach {
+                    station: r.station,
+                    target,
+                    actual,
+                })
+            })
+            .collect()
+    }
+}
+
+pub fn parse_target(s: &str) -> Result<f64, SlaError> {
+    let target: f64 = s
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| SlaError::InvalidTarget(s.to_string()))?;
+    if !(0.0..=100.0).contains(&target) {
+        return Err(SlaError::InvalidTarget(s.to_string()));
+    }
+    Ok(target)
+}
+
+/// Parse an SLA config file:
+/// default <percent>\n
+/// <station_id> <percent>\n
+///
+/// Blank lines and lines starting with `#` are ignored.
+pub fn parse_sla_config(input: &str) -> Result<SlaPolicy, SlaError> {
+    let mut policy = SlaPolicy::default();
+    for (line_idx, raw_line) in input.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() != 2 {
+            return Err(SlaError::InvalidConfig(format!(
+                "expected '<station_id|default> <percent>' at line {}",
+                line_idx + 1
+            )));
+        }
+        let target = parse_target(tokens[1])?;
+        if tokens[0] == "default" {
+            if policy.default_target.replace(target).is_some() {
+                return Err(SlaError::InvalidConfig(format!(
+                    "duplicate default target (line {})",
+                    line_idx + 1
+                )));
+            }
+            continue;
+        }
+        let station: u32 = tokens[0].parse().map_err(|_| {
+            SlaError::InvalidConfig(format!("invalid station id at line {}", line_idx + 1))
+        })?;
+        if policy
+            .overrides
+            .insert(StationId(station), target)
+            .is_some()
+        {
+            return Err(SlaError::InvalidConfig(format!(
+                "duplicate target for station {} (line {})",
+                station,
+                line_idx + 1
+            )));
+        }
+    }
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uptime(station: u32, available: u64, reported: u64) -> StationUptime {
+        StationUptime {
+            station: StationId(station),
+            percent: (available * 100 / reported) as u8,
+            raw_percent: (available * 100 / reported) as u8,
+            available,
+            reported,
+            excluded: 0,
+            excused: 0,
+            by_status: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn parse_config_with_default_and_overrides() {
+        let policy = parse_sla_config("# fleet\ndefault 97\n\n2 99.5\n").unwrap();
+        assert_eq!(policy.default_target, Some(97.0));
+        assert_eq!(policy.target_for(StationId(2)), Some(99.5));
+        assert_eq!(policy.target_for(StationId(3)), Some(97.0));
+    }
+
+    #[test]
+    fn parse_config_rejects_bad_lines() {
+        assert!(parse_sla_config("default\n").is_err());
+        assert!(parse_sla_config("1 101\n").is_err());
+        assert!(parse_sla_config("x 90\n").is_err());
+        assert!(parse_sla_config("1 90\n1 95\n").is_err());
+        let err = parse_sla_config("default 97\n1 90\ndefault 95\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid SLA config: duplicate default target (line 3)"
+        );
+    }
+
+    #[test]
+    fn breaches_use_exact_ratio() {
+        let mut policy = SlaPolicy {
+            default_target: Some(97.5),
+            ..SlaPolicy::default()
+        };
+        policy.overrides.insert(StationId(3), 50.0);
+        // 97.4% floors to 97 but is still a breach; 97.6% is not
+        let results = vec![
+            uptime(1, 974, 1000),
+            uptime(2, 976, 1000),
+            uptime(3, 600, 1000),
+        ];
+        let breaches = policy.breaches(&results);
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].station, StationId(1));
+        assert!(SlaPolicy::default().breaches(&results).is_empty());
+    }
+}
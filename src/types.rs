@@ -1,24 +1,469 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct StationId(pub u32);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct ChargerId(pub u32);
 
+/// A connector of a charger (EVSE), numbered within that charger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ConnectorId(pub u32);
+
+/// The `<charger>` or `<charger>:<connector>` token a report line starts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChargerToken {
+    pub charger: ChargerId,
+    pub connector: Option<ConnectorId>,
+}
+
+impl fmt::Display for ChargerToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.connector {
+            Some(connector) => write!(f, "{}:{}", self.charger.0, connector.0),
+            None => write!(f, "{}", self.charger.0),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("invalid charger id '{0}'")]
+pub struct InvalidChargerToken(pub String);
+
+impl FromStr for ChargerToken {
+    type Err = InvalidChargerToken;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidChargerToken(s.to_string());
+        let (charger, connector) = match s.split_once(':') {
+            Some((charger, connector)) => (
+                charger,
+                Some(ConnectorId(connector.parse().map_err(|_| invalid())?)),
+            ),
+            None => (s, None),
+        };
+        Ok(ChargerToken {
+            charger: ChargerId(charger.parse().map_err(|_| invalid())?),
+            connector,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Interval {
     pub start: u64,
     pub end: u64,
 }
 
-#[derive(Debug, Clone)]
+/// Unit of the timestamps in an input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TimeUnit {
+    #[cfg_attr(feature = "serde", serde(rename = "ns"))]
+    Nanoseconds,
+    #[cfg_attr(feature = "serde", serde(rename = "us"))]
+    Microseconds,
+    #[cfg_attr(feature = "serde", serde(rename = "ms"))]
+    Milliseconds,
+    #[cfg_attr(feature = "serde", serde(rename = "s"))]
+    Seconds,
+}
+
+impl TimeUnit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeUnit::Nanoseconds => "ns",
+            TimeUnit::Microseconds => "us",
+            TimeUnit::Milliseconds => "ms",
+            TimeUnit::Seconds => "s",
+        }
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("unknown time unit '{0}', expected ns|us|ms|s")]
+pub struct UnknownTimeUnit(pub String);
+
+impl FromStr for TimeUnit {
+    type Err = UnknownTimeUnit;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ns" => Ok(TimeUnit::Nanoseconds),
+            "us" => Ok(TimeUnit::Microseconds),
+            "ms" => Ok(TimeUnit::Milliseconds),
+            "s" => Ok(TimeUnit::Seconds),
+            _ => Err(UnknownTimeUnit(s.to_string())),
+        }
+    }
+}
+
+/// Operational state a charger reported for an interval.
+///
+/// `Up` and `Down` are the plain `true`/`false` flags of the original format;
+/// the remaining variants carry finer-grained operational detail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChargerStatus {
+    Up,
+    Down,
+    Charging,
+    Idle,
+    Faulted,
+    Maintenance,
+}
+
+impl ChargerStatus {
+    pub const ALL: [ChargerStatus; 6] = [
+        ChargerStatus::Up,
+        ChargerStatus::Down,
+        ChargerStatus::Charging,
+        ChargerStatus::Idle,
+        ChargerStatus::Faulted,
+        ChargerStatus::Maintenance,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChargerStatus::Up => "up",
+            ChargerStatus::Down => "down",
+            ChargerStatus::Charging => "charging",
+            ChargerStatus::Idle => "idle",
+            ChargerStatus::Faulted => "faulted",
+            ChargerStatus::Maintenance => "maintenance",
+        }
+    }
+}
+
+impl From<bool> for ChargerStatus {
+    fn from(up: bool) -> Self {
+        if up {
+            ChargerStatus::Up
+        } else {
+            ChargerStatus::Down
+        }
+    }
+}
+
+impl fmt::Display for ChargerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("unknown charger status '{0}'")]
+pub struct UnknownStatus(pub String);
+
+impl FromStr for ChargerStatus {
+    type Err = UnknownStatus;
+
+    /// Accepts the legacy `true`/`false` flags as well as the status names.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "true" => Ok(ChargerStatus::Up),
+            "false" => Ok(ChargerStatus::Down),
+            _ => ChargerStatus::ALL
+                .into_iter()
+                .find(|status| status.as_str() == s)
+                .ok_or_else(|| UnknownStatus(s.to_string())),
+        }
+    }
+}
+
+/// Serialized as the status name; deserialized from a name or, like the text
+/// format, from a `true`/`false` flag.
+#[cfg(feature = "serde")]
+impl Serialize for ChargerStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ChargerStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Flag(bool),
+            Name(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Flag(up) => Ok(up.into()),
+            Repr::Name(name) => name.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Serialized flat, as `{"charger": 1, "start": 0, "end": 10, "status": "up"}`,
+/// with a `"connector"` field only when the report names one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ChargerReport {
     pub charger: ChargerId,
+    /// The connector this report is for, or `None` for the charger as a whole.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub connector: Option<ConnectorId>,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub interval: Interval,
-    pub up: bool,
+    pub status: ChargerStatus,
 }
 
-#[derive(Debug, Clone)]
+impl ChargerReport {
+    /// The charger and connector this report is for.
+    pub fn token(&self) -> ChargerToken {
+        ChargerToken {
+            charger: self.charger,
+            connector: self.connector,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Station {
     pub id: StationId,
     pub chargers: Vec<ChargerId>,
 }
+
+/// Descriptive fields of a station, carried through to the outputs. Keys
+/// other than `name`, `region`, `operator` and `network` are tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StationMetadata {
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub name: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub region: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub operator: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub network: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl StationMetadata {
+    pub const FIELDS: [&'static str; 4] = ["name", "region", "operator", "network"];
+
+    /// Column names of the CLI's CSV outputs. Metadata keys become columns
+    /// next to them, so they cannot reuse these names.
+    pub const RESERVED_KEYS: [&'static str; 18] = [
+        "station",
+        "stations",
+        "charger",
+        "connector",
+        "start",
+        "end",
+        "uptime",
+        "raw_uptime",
+        "available",
+        "reported",
+        "excluded",
+        "excused",
+        "no_data",
+        "min",
+        "max",
+        "p5",
+        "p50",
+        "p95",
+    ];
+
+    /// Check that an entry survives every output and reads back the same from
+    /// the text format, where a line is `<station> <key> <value>` and `#`
+    /// starts a comment: keys are single words outside `RESERVED_KEYS`, and
+    /// values hold no `#` or line break.
+    pub fn check(key: &str, value: &str) -> Result<(), String> {
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(format!(
+                "invalid metadata key '{key}': keys cannot contain whitespace"
+            ));
+        }
+        if Self::RESERVED_KEYS.contains(&key) {
+            return Err(format!(
+                "metadata key '{key}' is reserved for an output column"
+            ));
+        }
+        if value.contains(['#', '\n', '\r']) {
+            return Err(format!(
+                "metadata value '{value}' for '{key}' cannot contain '#' or line breaks"
+            ));
+        }
+        Ok(())
+    }
+
+    /// The value stored under `key`, a field name or a tag.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            "name" => self.name.as_deref(),
+            "region" => self.region.as_deref(),
+            "operator" => self.operator.as_deref(),
+            "network" => self.network.as_deref(),
+            _ => self.tags.get(key).map(String::as_str),
+        }
+    }
+
+    /// Store `value` under `key`. Returns `false`, leaving the old value, if
+    /// the key is already set.
+    pub fn set(&mut self, key: &str, value: String) -> bool {
+        let slot = match key {
+            "name" => &mut self.name,
+            "region" => &mut self.region,
+            "operator" => &mut self.operator,
+            "network" => &mut self.network,
+            _ => {
+                if self.tags.contains_key(key) {
+                    return false;
+                }
+                self.tags.insert(key.to_string(), value);
+                return true;
+            }
+        };
+        if slot.is_some() {
+            return false;
+        }
+        *slot = Some(value);
+        true
+    }
+
+    /// Every set key and value: the fields in `FIELDS` order, then the tags.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        Self::FIELDS
+            .into_iter()
+            .filter_map(|key| Some((key, self.get(key)?)))
+            .chain(self.tags.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    }
+}
+
+/// A charger belonging to a station for a limited time. A charger with
+/// assignments only counts towards a station while assigned to it; an
+/// open-ended assignment has `interval.end == u64::MAX`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChargerAssignment {
+    pub charger: ChargerId,
+    pub station: StationId,
+    pub interval: Interval,
+}
+
+/// Entity a time window applies to: every charger of a station, or one charger.
+/// Serialized as `{"station": 1}` or `{"charger": 100}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum WindowTarget {
+    Station(StationId),
+    Charger(ChargerId),
+}
+
+impl WindowTarget {
+    /// Whether the window covers `charger`, which belongs to `station`.
+    pub fn covers(&self, station: StationId, charger: ChargerId) -> bool {
+        match *self {
+            WindowTarget::Station(id) => id == station,
+            WindowTarget::Charger(id) => id == charger,
+        }
+    }
+}
+
+/// Planned maintenance, excluded from both sides of the uptime ratio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MaintenanceWindow {
+    pub target: WindowTarget,
+    pub interval: Interval,
+}
+
+/// Reason an outage happened, used to decide whether it is excused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OutageCategory {
+    GridOutage,
+    Vandalism,
+    ForceMajeure,
+    Other,
+}
+
+impl OutageCategory {
+    pub const ALL: [OutageCategory; 4] = [
+        OutageCategory::GridOutage,
+        OutageCategory::Vandalism,
+        OutageCategory::ForceMajeure,
+        OutageCategory::Other,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutageCategory::GridOutage => "grid_outage",
+            OutageCategory::Vandalism => "vandalism",
+            OutageCategory::ForceMajeure => "force_majeure",
+            OutageCategory::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for OutageCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("unknown outage category '{0}'")]
+pub struct UnknownCategory(pub String);
+
+impl FromStr for OutageCategory {
+    type Err = UnknownCategory;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutageCategory::ALL
+            .into_iter()
+            .find(|category| category.as_str() == s)
+            .ok_or_else(|| UnknownCategory(s.to_string()))
+    }
+}
+
+/// An outage tagged with its cause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OutageWindow {
+    pub target: WindowTarget,
+    pub interval: Interval,
+    pub category: OutageCategory,
+}
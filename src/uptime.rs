@@ -1,5 +1,10 @@
-use crate::interval::merge_intervals;
-use crate::types::{ChargerId, ChargerReport, Interval, Station, StationId};
+use crate::interval::{intersect_intervals, merge_intervals, subtract_intervals, total_duration};
+use crate::policy::{ExcusePolicy, StatusClass, StatusPolicy};
+use crate::types::{
+    ChargerAssignment, ChargerId, ChargerReport, ChargerStatus, ConnectorId, Interval,
+    MaintenanceWindow, OutageWindow, Station, StationId,
+};
+use std::collections::BTreeMap;
 
 #[derive(thiserror::Error, Debug)]
 pub enum UptimeError {
@@ -7,86 +12,494 @@ pub enum UptimeError {
     NoReporting(StationId),
 }
 
+/// Knobs for the uptime computation. `Default` reproduces the original
+/// boolean `up`/`down` semantics.
+#[derive(Debug, Clone, Default)]
+pub struct UptimeConfig {
+    pub status_policy: StatusPolicy,
+    /// Time-bounded station membership. Reports of an assigned charger only
+    /// count towards the station it was assigned to at the time.
+    pub assignments: Vec<ChargerAssignment>,
+    /// Planned maintenance removed from both numerator and denominator.
+    pub maintenance: Vec<MaintenanceWindow>,
+    /// Tagged outages; those whose category is excused only affect `percent`.
+    pub outages: Vec<OutageWindow>,
+    pub excuse_policy: ExcusePolicy,
+    /// How a charger's connectors combine into the charger's availability.
+    pub connector_rollup: Rollup,
+    /// How a station's chargers combine into the station's availability.
+    pub charger_rollup: Rollup,
+}
+
+/// How one level's availability follows from the level below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rollup {
+    /// Up while any reporting member is up.
+    #[default]
+    Any,
+    /// Up only while every reporting member is up.
+    All,
+}
+
+/// Uptime figures for one station. Durations are in the input's time unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StationUptime {
+    pub station: StationId,
+    /// Floored uptime percentage in `[0, 100]`, after excusing outages.
+    pub percent: u8,
+    /// Floored uptime percentage with excused outages still counted as downtime.
+    pub raw_percent: u8,
+    /// Time counted as available (the numerator).
+    pub available: u64,
+    /// Time counted towards the denominator.
+    pub reported: u64,
+    /// Time inside the reporting window removed from the denominator, either
+    /// by an excluded status or by a maintenance window.
+    pub excluded: u64,
+    /// Additional reporting time removed by excused outages.
+    pub excused: u64,
+    /// Time spent in each reported status, unioned across the station's chargers.
+    pub by_status: BTreeMap<ChargerStatus, u64>,
+}
+
+/// Floored uptime percentage per station under `UptimeConfig::default()`.
+/// Maintenance windows, outages, charger assignments and status
+/// reclassification are ignored; use `compute_station_uptime_with_maintenance`
+/// or `compute_station_report` to apply them.
 pub fn compute_station_uptime(
     stations: &[Station],
     reports: &[ChargerReport],
 ) -> Result<Vec<(StationId, u8)>, UptimeError> {
-    // Index reports by charger for efficient lookup per station.
-    let mut reports_by_charger: std::collections::BTreeMap<ChargerId, Vec<&ChargerReport>> =
-        std::collections::BTreeMap::new();
+    compute_station_uptime_with_maintenance(stations, reports, &[])
+}
+
+/// Like `compute_station_uptime`, but time inside `maintenance` is removed
+/// from both numerator and denominator. Outages, charger assignments and
+/// status reclassification are still ignored.
+pub fn compute_station_uptime_with_maintenance(
+    stations: &[Station],
+    reports: &[ChargerReport],
+    maintenance: &[MaintenanceWindow],
+) -> Result<Vec<(StationId, u8)>, UptimeError> {
+    let config = UptimeConfig {
+        maintenance: maintenance.to_vec(),
+        ..UptimeConfig::default()
+    };
+    let report = compute_station_report(stations, reports, &config)?;
+    Ok(report.into_iter().map(|s| (s.station, s.percent)).collect())
+}
+
+/// Per-station outcome when one station without data should not abort the run.
+/// Serialized as `{"computed": {...}}` or `{"no_data": 2}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StationResult {
+    Computed(StationUptime),
+    /// The station had no reporting time left to compute a percentage from.
+    NoData(StationId),
+}
+
+impl StationResult {
+    pub fn station(&self) -> StationId {
+        match self {
+            StationResult::Computed(r) => r.station,
+            StationResult::NoData(id) => *id,
+        }
+    }
+}
+
+pub fn compute_station_report(
+    stations: &[Station],
+    reports: &[ChargerReport],
+    config: &UptimeConfig,
+) -> Result<Vec<StationUptime>, UptimeError> {
+    compute_station_results(stations, reports, config)
+        .into_iter()
+        .map(|result| match result {
+            StationResult::Computed(r) => Ok(r),
+            // Policy: surface an error if no charger reported for this station
+            StationResult::NoData(id) => Err(UptimeError::NoReporting(id)),
+        })
+        .collect()
+}
+
+/// Like `compute_station_report`, but stations without reporting time become
+/// `StationResult::NoData` instead of failing the whole computation.
+pub fn compute_station_results(
+    stations: &[Station],
+    reports: &[ChargerReport],
+    config: &UptimeConfig,
+) -> Vec<StationResult> {
+    let reports_by_charger = index_reports(reports);
+
+    let mut results: Vec<StationResult> = stations
+        .iter()
+        .map(|station| {
+            let timeline = station_timeline(station, &reports_by_charger, config);
+            result_from(station.id, &timeline)
+        })
+        .collect();
+
+    // Output must be sorted by station id ascending
+    results.sort_by_key(|r| r.station());
+    results
+}
+
+/// Level below stations that `compute_unit_results` reports on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitLevel {
+    Charger,
+    Connector,
+}
+
+/// Uptime of one charger, or one of its connectors, at one station. The
+/// inner result names that station.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnitResult {
+    pub charger: ChargerId,
+    /// Always `None` at charger level; at connector level, `None` holds the
+    /// reports that name no connector.
+    pub connector: Option<ConnectorId>,
+    pub result: StationResult,
+}
+
+/// Per-charger or per-connector figures, computed like station figures.
+/// Chargers without reports yield `StationResult::NoData`; a charger assigned
+/// to several stations gets one row per station. Sorted by station, charger
+/// and connector.
+pub fn compute_unit_results(
+    stations: &[Station],
+    reports: &[ChargerReport],
+    config: &UptimeConfig,
+    level: UnitLevel,
+) -> Vec<UnitResult> {
+    let reports_by_charger = index_reports(reports);
+
+    let mut results: Vec<UnitResult> = Vec::new();
+    for station in stations {
+        for (charger, membership) in station_members(station, &config.assignments) {
+            let list = reports_by_charger
+                .get(&charger)
+                .map_or(&[][..], Vec::as_slice);
+            let membership = membership.as_deref();
+            if level == UnitLevel::Charger || list.is_empty() {
+                let timeline = charger_timeline(station.id, charger, membership, list, config);
+                results.push(UnitResult {
+                    charger,
+                    connector: None,
+                    result: result_from(station.id, &timeline),
+                });
+                continue;
+            }
+            for (connector, group) in by_connector(list) {
+                let timeline = leaf_timeline(station.id, charger, membership, &group, config);
+                results.push(UnitResult {
+                    charger,
+                    connector,
+                    result: result_from(station.id, &timeline),
+                });
+            }
+        }
+    }
+
+    results.sort_by_key(|r| (r.result.station(), r.charger, r.connector));
+    results
+}
+
+fn result_from(station: StationId, timeline: &Timeline) -> StationResult {
+    let raw_reported = total_duration(&timeline.raw_counted);
+    let reported_duration = total_duration(&timeline.counted);
+    if reported_duration == 0 {
+        return StationResult::NoData(station);
+    }
+    let raw_up_duration = total_duration(&timeline.raw_up);
+    let up_duration = total_duration(&timeline.up);
+
+    StationResult::Computed(StationUptime {
+        station,
+        percent: floor_percent(up_duration, reported_duration),
+        raw_percent: floor_percent(raw_up_duration, raw_reported),
+        available: up_duration,
+        reported: reported_duration,
+        excluded: total_duration(&timeline.reporting) - raw_reported,
+        excused: raw_reported - reported_duration,
+        by_status: timeline
+            .by_status
+            .iter()
+            .map(|(status, ivs)| (*status, total_duration(ivs)))
+            .collect(),
+    })
+}
+
+/// The periods each station counted as down: the adjusted denominator minus the
+/// adjusted numerator. Stations without reports have no outages.
+pub fn compute_station_outages(
+    stations: &[Station],
+    reports: &[ChargerReport],
+    config: &UptimeConfig,
+) -> Vec<(StationId, Vec<Interval>)> {
+    let reports_by_charger = index_reports(reports);
+    let mut results: Vec<(StationId, Vec<Interval>)> = stations
+        .iter()
+        .map(|station| {
+            let timeline = station_timeline(station, &reports_by_charger, config);
+            (
+                station.id,
+                subtract_intervals(&timeline.counted, &timeline.up),
+            )
+        })
+        .collect();
+    results.sort_by_key(|(sid, _)| *sid);
+    results
+}
+
+/// Merged interval sets behind the figures of a station, charger or connector.
+#[derive(Default)]
+struct Timeline {
+    /// Union of the reporting spans.
+    reporting: Vec<Interval>,
+    raw_counted: Vec<Interval>,
+    raw_up: Vec<Interval>,
+    counted: Vec<Interval>,
+    up: Vec<Interval>,
+    by_status: BTreeMap<ChargerStatus, Vec<Interval>>,
+}
+
+// Index reports by charger for efficient lookup per station.
+fn index_reports(reports: &[ChargerReport]) -> BTreeMap<ChargerId, Vec<&ChargerReport>> {
+    let mut reports_by_charger: BTreeMap<ChargerId, Vec<&ChargerReport>> = BTreeMap::new();
     for r in reports {
         reports_by_charger.entry(r.charger).or_default().push(r);
     }
+    reports_by_charger
+}
 
-    let mut results: Vec<(StationId, u8)> = Vec::with_capacity(stations.len());
-    for station in stations {
-        // Denominator strategy:
-        // For each charger present at this station, find its overall reporting SPAN
-        // defined as [min(start), max(end)). Gaps inside this span count as downtime,
-        // per the prompt. The station denominator is the UNION of each charger's span.
-        let mut reporting_spans: Vec<Interval> = Vec::new();
-
-        // Numerator strategy:
-        // Union of all intervals marked up == true across all chargers at the station.
-        let mut all_up: Vec<Interval> = Vec::new();
-
-        for charger in &station.chargers {
-            if let Some(list) = reports_by_charger.get(charger) {
-                let mut min_start: Option<u64> = None;
-                let mut max_end: Option<u64> = None;
-
-                for r in list.iter().copied() {
-                    // Track charger span for denominator
-                    min_start = Some(match min_start {
-                        Some(s) => s.min(r.interval.start),
-                        None => r.interval.start,
-                    });
-                    max_end = Some(match max_end {
-                        Some(e) => e.max(r.interval.end),
-                        None => r.interval.end,
-                    });
-
-                    // Track up intervals for numerator
-                    if r.up {
-                        all_up.push(r.interval);
-                    }
-                }
+/// A charger's reports split by connector; reports naming no connector form
+/// their own group.
+fn by_connector<'a>(
+    reports: &[&'a ChargerReport],
+) -> BTreeMap<Option<ConnectorId>, Vec<&'a ChargerReport>> {
+    let mut groups: BTreeMap<Option<ConnectorId>, Vec<&ChargerReport>> = BTreeMap::new();
+    for r in reports {
+        groups.entry(r.connector).or_default().push(r);
+    }
+    groups
+}
 
-                if let (Some(s), Some(e)) = (min_start, max_end) {
-                    if e > s {
-                        reporting_spans.push(Interval { start: s, end: e });
-                    }
-                }
-            }
+fn station_timeline(
+    station: &Station,
+    reports_by_charger: &BTreeMap<ChargerId, Vec<&ChargerReport>>,
+    config: &UptimeConfig,
+) -> Timeline {
+    let chargers = station_members(station, &config.assignments)
+        .into_iter()
+        .filter_map(|(charger, membership)| {
+            let list = reports_by_charger.get(&charger)?;
+            Some(charger_timeline(
+                station.id,
+                charger,
+                membership.as_deref(),
+                list,
+                config,
+            ))
+        })
+        .collect();
+    combine(chargers, config.charger_rollup)
+}
+
+/// A charger's timeline at `station`, rolled up from its connectors.
+fn charger_timeline(
+    station: StationId,
+    charger: ChargerId,
+    membership: Option<&[Interval]>,
+    reports: &[&ChargerReport],
+    config: &UptimeConfig,
+) -> Timeline {
+    let connectors = by_connector(reports)
+        .values()
+        .map(|list| leaf_timeline(station, charger, membership, list, config))
+        .collect();
+    combine(connectors, config.connector_rollup)
+}
+
+/// The timeline of one connector, or of a charger reporting without
+/// connectors, from its own reports.
+fn leaf_timeline(
+    station: StationId,
+    charger: ChargerId,
+    membership: Option<&[Interval]>,
+    reports: &[&ChargerReport],
+    config: &UptimeConfig,
+) -> Timeline {
+    // Denominator strategy:
+    // The reporting SPAN is [min(start), max(end)). Gaps inside this span
+    // count as downtime, per the prompt. Time spent in an excluded status or
+    // under a maintenance window is cut out of the span. Excused outages are
+    // cut out as well, but only for the adjusted figures; the raw figures
+    // keep them as downtime.
+    //
+    // Numerator strategy:
+    // Union of the available intervals, minus the same excluded time.
+
+    // Reports from while the charger was elsewhere do not count here
+    let clipped: Vec<(Interval, ChargerStatus)> = reports
+        .iter()
+        .flat_map(|r| {
+            let pieces = match membership {
+                None => vec![r.interval],
+                Some(windows) => intersect_intervals(&[r.interval], windows),
+            };
+            pieces.into_iter().map(|iv| (iv, r.status))
+        })
+        .collect();
+    let mut min_start: Option<u64> = None;
+    let mut max_end: Option<u64> = None;
+    let mut available: Vec<Interval> = Vec::new();
+    let mut excluded: Vec<Interval> = config
+        .maintenance
+        .iter()
+        .filter(|w| w.target.covers(station, charger))
+        .map(|w| w.interval)
+        .collect();
+    let mut by_status: BTreeMap<ChargerStatus, Vec<Interval>> = BTreeMap::new();
+
+    for (interval, status) in clipped {
+        // Track span for denominator
+        min_start = Some(match min_start {
+            Some(s) => s.min(interval.start),
+            None => interval.start,
+        });
+        max_end = Some(match max_end {
+            Some(e) => e.max(interval.end),
+            None => interval.end,
+        });
+
+        match config.status_policy.classify(status) {
+            StatusClass::Available => available.push(interval),
+            StatusClass::Unavailable => {}
+            StatusClass::Excluded => excluded.push(interval),
         }
+        by_status.entry(status).or_default().push(interval);
+    }
 
-        // The denominator is the total time covered by the union of charger spans
-        let merged_reporting = merge_intervals(&mut reporting_spans);
-        let merged_up = merge_intervals(&mut all_up);
+    let (Some(s), Some(e)) = (min_start, max_end) else {
+        return Timeline::default();
+    };
+    if e <= s {
+        return Timeline::default();
+    }
+    let span = Interval { start: s, end: e };
+    // Gaps between two stints at this station are not downtime here
+    let span = match membership {
+        None => vec![span],
+        Some(windows) => intersect_intervals(&[span], windows),
+    };
+    let available = merge_intervals(&mut available);
+    let mut excused: Vec<Interval> = config
+        .outages
+        .iter()
+        .filter(|o| o.target.covers(station, charger))
+        .filter(|o| config.excuse_policy.is_excused(o.category))
+        .map(|o| o.interval)
+        .chain(excluded.iter().copied())
+        .collect();
+    let excused = merge_intervals(&mut excused);
+    let excluded = merge_intervals(&mut excluded);
 
-        let reported_duration: u128 = merged_reporting
+    Timeline {
+        raw_counted: subtract_intervals(&span, &excluded),
+        raw_up: subtract_intervals(&available, &excluded),
+        counted: subtract_intervals(&span, &excused),
+        up: subtract_intervals(&available, &excused),
+        reporting: span,
+        by_status: by_status
+            .into_iter()
+            .map(|(status, mut ivs)| (status, merge_intervals(&mut ivs)))
+            .collect(),
+    }
+}
+
+/// Roll the timelines of a station's chargers, or of a charger's connectors,
+/// up one level. Counted time is the union of the parts' counted time. With
+/// `Rollup::Any` the whole is up while any part is up; with `Rollup::All`
+/// it is up while no part that counts at the time is down.
+fn combine(parts: Vec<Timeline>, rollup: Rollup) -> Timeline {
+    let union = |pick: fn(&Timeline) -> &Vec<Interval>| {
+        let mut all: Vec<Interval> = parts.iter().flat_map(|p| pick(p).clone()).collect();
+        merge_intervals(&mut all)
+    };
+    let down = |counted: fn(&Timeline) -> &Vec<Interval>, up: fn(&Timeline) -> &Vec<Interval>| {
+        let mut all: Vec<Interval> = parts
             .iter()
-            .map(|iv| (iv.end - iv.start) as u128)
-            .sum();
-        if reported_duration == 0 {
-            // Policy: surface an error if no charger reported for this station
-            return Err(UptimeError::NoReporting(station.id));
+            .flat_map(|p| subtract_intervals(counted(p), up(p)))
+            .collect();
+        merge_intervals(&mut all)
+    };
+
+    let raw_counted = union(|t| &t.raw_counted);
+    let counted = union(|t| &t.counted);
+    let (raw_up, up) = match rollup {
+        Rollup::Any => (union(|t| &t.raw_up), union(|t| &t.up)),
+        Rollup::All => (
+            subtract_intervals(&raw_counted, &down(|t| &t.raw_counted, |t| &t.raw_up)),
+            subtract_intervals(&counted, &down(|t| &t.counted, |t| &t.up)),
+        ),
+    };
+
+    let mut by_status: BTreeMap<ChargerStatus, Vec<Interval>> = BTreeMap::new();
+    for part in &parts {
+        for (status, ivs) in &part.by_status {
+            by_status.entry(*status).or_default().extend(ivs);
         }
-        let up_duration: u128 = merged_up.iter().map(|iv| (iv.end - iv.start) as u128).sum();
+    }
+    Timeline {
+        reporting: union(|t| &t.reporting),
+        raw_counted,
+        raw_up,
+        counted,
+        up,
+        by_status: by_status
+            .into_iter()
+            .map(|(status, mut ivs)| (status, merge_intervals(&mut ivs)))
+            .collect(),
+    }
+}
 
-        let percent = ((up_duration.saturating_mul(100)) / reported_duration) as u8; // floor division
-        results.push((station.id, percent));
+/// The chargers counted towards `station`, each with the merged periods it
+/// was assigned there, or `None` for a charger listed in `[Stations]`.
+fn station_members(
+    station: &Station,
+    assignments: &[ChargerAssignment],
+) -> Vec<(ChargerId, Option<Vec<Interval>>)> {
+    let mut members: Vec<(ChargerId, Option<Vec<Interval>>)> =
+        station.chargers.iter().map(|c| (*c, None)).collect();
+    let mut assigned: BTreeMap<ChargerId, Vec<Interval>> = BTreeMap::new();
+    for a in assignments.iter().filter(|a| a.station == station.id) {
+        assigned.entry(a.charger).or_default().push(a.interval);
     }
+    members.extend(
+        assigned
+            .into_iter()
+            .map(|(charger, mut windows)| (charger, Some(merge_intervals(&mut windows)))),
+    );
+    members
+}
 
-    // Output must be sorted by station id ascending
-    results.sort_by_key(|(sid, _)| *sid);
-    Ok(results)
+/// `floor(100 * part / whole)`, computed in `u128` to avoid overflow.
+fn floor_percent(part: u64, whole: u64) -> u8 {
+    if whole == 0 {
+        return 0;
+    }
+    ((part as u128 * 100) / whole as u128) as u8
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{ChargerId, Interval, Station};
+    use crate::types::{ChargerId, ChargerStatus, Interval, OutageCategory, Station, WindowTarget};
 
     #[test]
     fn single_charger_full_uptime() {
@@ -96,8 +509,9 @@ mod tests {
         }];
         let reports = vec![ChargerReport {
             charger: ChargerId(10),
+            connector: None,
             interval: Interval { start: 0, end: 100 },
-            up: true,
+            status: ChargerStatus::Up,
         }];
         let res = compute_station_uptime(&stations, &reports).unwrap();
         assert_eq!(res, vec![(StationId(1), 100)]);
@@ -112,17 +526,19 @@ mod tests {
         let reports = vec![
             ChargerReport {
                 charger: ChargerId(10),
+                connector: None,
                 interval: Interval { start: 0, end: 50 },
-                up: true,
+                status: ChargerStatus::Up,
             },
             // gap 50..70 counts as down
             ChargerReport {
                 charger: ChargerId(10),
+                connector: None,
                 interval: Interval {
                     start: 70,
                     end: 100,
                 },
-                up: true,
+                status: ChargerStatus::Up,
             },
         ];
         let res = compute_station_uptime(&stations, &reports).unwrap();
@@ -144,16 +560,18 @@ mod tests {
         let reports = vec![
             ChargerReport {
                 charger: ChargerId(10),
+                connector: None,
                 interval: Interval { start: 0, end: 50 },
-                up: true,
+                status: ChargerStatus::Up,
             },
             ChargerReport {
                 charger: ChargerId(11),
+                connector: None,
                 interval: Interval {
                     start: 30,
                     end: 120,
                 },
-                up: true,
+                status: ChargerStatus::Up,
             },
         ];
         let res = compute_station_uptime(&stations, &reports).unwrap();
@@ -170,19 +588,387 @@ mod tests {
         let reports = vec![
             ChargerReport {
                 charger: ChargerId(10),
+                connector: None,
                 interval: Interval { start: 0, end: 99 },
-                up: true,
+                status: ChargerStatus::Up,
             },
             ChargerReport {
                 charger: ChargerId(10),
+                connector: None,
                 interval: Interval {
                     start: 99,
                     end: 100,
                 },
-                up: false,
+                status: ChargerStatus::Down,
             },
         ];
         let res = compute_station_uptime(&stations, &reports).unwrap();
         assert_eq!(res, vec![(StationId(1), 99)]);
     }
+
+    #[test]
+    fn statuses_follow_policy_and_excluded_time_leaves_denominator() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10)],
+        }];
+        let reports = vec![
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval { start: 0, end: 40 },
+                status: ChargerStatus::Charging,
+            },
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval { start: 40, end: 60 },
+                status: ChargerStatus::Faulted,
+            },
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval {
+                    start: 60,
+                    end: 100,
+                },
+                status: ChargerStatus::Maintenance,
+            },
+        ];
+        let res = compute_station_report(&stations, &reports, &UptimeConfig::default()).unwrap();
+        assert_eq!(res[0].percent, 66); // 40 up of 60 counted
+        assert_eq!(res[0].reported, 60);
+        assert_eq!(res[0].excluded, 40);
+        assert_eq!(res[0].by_status[&ChargerStatus::Charging], 40);
+        assert_eq!(res[0].by_status[&ChargerStatus::Faulted], 20);
+        assert_eq!(res[0].by_status[&ChargerStatus::Maintenance], 40);
+
+        let mut config = UptimeConfig::default();
+        config
+            .status_policy
+            .set(ChargerStatus::Maintenance, StatusClass::Unavailable);
+        let res = compute_station_report(&stations, &reports, &config).unwrap();
+        assert_eq!(res[0].percent, 40);
+        assert_eq!(res[0].excluded, 0);
+    }
+
+    #[test]
+    fn maintenance_windows_leave_numerator_and_denominator() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10), ChargerId(11)],
+        }];
+        let reports = vec![
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval { start: 0, end: 100 },
+                status: ChargerStatus::Up,
+            },
+            ChargerReport {
+                charger: ChargerId(11),
+                connector: None,
+                interval: Interval { start: 0, end: 100 },
+                status: ChargerStatus::Down,
+            },
+        ];
+        let config = UptimeConfig {
+            maintenance: vec![
+                // Station-wide: both chargers are excluded for [0, 20)
+                MaintenanceWindow {
+                    target: WindowTarget::Station(StationId(1)),
+                    interval: Interval { start: 0, end: 20 },
+                },
+                // Charger 10 only: charger 11 is still reporting down
+                MaintenanceWindow {
+                    target: WindowTarget::Charger(ChargerId(10)),
+                    interval: Interval { start: 60, end: 80 },
+                },
+            ],
+            ..UptimeConfig::default()
+        };
+        let res = compute_station_report(&stations, &reports, &config).unwrap();
+        assert_eq!(res[0].reported, 80);
+        assert_eq!(res[0].available, 60);
+        assert_eq!(res[0].excluded, 20);
+        assert_eq!(res[0].percent, 75);
+
+        let res = compute_station_uptime_with_maintenance(&stations, &reports, &config.maintenance)
+            .unwrap();
+        assert_eq!(res, vec![(StationId(1), 75)]);
+        let res = compute_station_uptime(&stations, &reports).unwrap();
+        assert_eq!(res, vec![(StationId(1), 100)]);
+    }
+
+    #[test]
+    fn excused_outages_only_adjust_percent() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10)],
+        }];
+        let reports = vec![
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval { start: 0, end: 50 },
+                status: ChargerStatus::Up,
+            },
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval {
+                    start: 50,
+                    end: 100,
+                },
+                status: ChargerStatus::Down,
+            },
+        ];
+        let config = UptimeConfig {
+            outages: vec![
+                OutageWindow {
+                    target: WindowTarget::Station(StationId(1)),
+                    interval: Interval { start: 50, end: 75 },
+                    category: OutageCategory::GridOutage,
+                },
+                OutageWindow {
+                    target: WindowTarget::Charger(ChargerId(10)),
+                    interval: Interval {
+                        start: 75,
+                        end: 100,
+                    },
+                    category: OutageCategory::Other,
+                },
+            ],
+            ..UptimeConfig::default()
+        };
+        let res = compute_station_report(&stations, &reports, &config).unwrap();
+        assert_eq!(res[0].raw_percent, 50);
+        assert_eq!(res[0].percent, 66); // 50 up of 75 after excusing the grid outage
+        assert_eq!(res[0].excused, 25);
+        assert_eq!(res[0].excluded, 0);
+    }
+
+    #[test]
+    fn outages_are_counted_time_that_was_not_up() {
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10)],
+        }];
+        let reports = vec![
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval { start: 0, end: 50 },
+                status: ChargerStatus::Up,
+            },
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval { start: 70, end: 80 },
+                status: ChargerStatus::Down,
+            },
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval {
+                    start: 80,
+                    end: 100,
+                },
+                status: ChargerStatus::Maintenance,
+            },
+        ];
+        let res = compute_station_outages(&stations, &reports, &UptimeConfig::default());
+        // The gap and the down report merge; maintenance is not an outage
+        assert_eq!(
+            res,
+            vec![(StationId(1), vec![Interval { start: 50, end: 80 }])]
+        );
+    }
+
+    #[test]
+    fn station_without_reports_is_no_data_not_an_error() {
+        let stations = vec![
+            Station {
+                id: StationId(2),
+                chargers: vec![ChargerId(20)],
+            },
+            Station {
+                id: StationId(1),
+                chargers: vec![ChargerId(10)],
+            },
+        ];
+        let reports = vec![ChargerReport {
+            charger: ChargerId(10),
+            connector: None,
+            interval: Interval { start: 0, end: 100 },
+            status: ChargerStatus::Up,
+        }];
+        let config = UptimeConfig::default();
+        let res = compute_station_results(&stations, &reports, &config);
+        assert_eq!(res.len(), 2);
+        assert!(matches!(&res[0], StationResult::Computed(r) if r.percent == 100));
+        assert_eq!(res[1], StationResult::NoData(StationId(2)));
+
+        assert!(matches!(
+            compute_station_report(&stations, &reports, &config),
+            Err(UptimeError::NoReporting(StationId(2)))
+        ));
+    }
+
+    #[test]
+    fn excluded_charger_does_not_hide_other_chargers() {
+        // C10 is in maintenance while C11 is up: the station still counts as up.
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10), ChargerId(11)],
+        }];
+        let reports = vec![
+            ChargerReport {
+                charger: ChargerId(10),
+                connector: None,
+                interval: Interval { start: 0, end: 100 },
+                status: ChargerStatus::Maintenance,
+            },
+            ChargerReport {
+                charger: ChargerId(11),
+                connector: None,
+                interval: Interval { start: 0, end: 50 },
+                status: ChargerStatus::Up,
+            },
+        ];
+        let res = compute_station_report(&stations, &reports, &UptimeConfig::default()).unwrap();
+        assert_eq!(res[0].percent, 100);
+        assert_eq!(res[0].reported, 50);
+        assert_eq!(res[0].excluded, 50);
+    }
+
+    #[test]
+    fn assigned_charger_counts_where_it_was() {
+        // C200 is at station 1, moves to station 2 at t=50 and back at t=80.
+        let stations = vec![
+            Station {
+                id: StationId(1),
+                chargers: vec![ChargerId(100)],
+            },
+            Station {
+                id: StationId(2),
+                chargers: vec![],
+            },
+        ];
+        let assign = |station: u32, start: u64, end: u64| ChargerAssignment {
+            charger: ChargerId(200),
+            station: StationId(station),
+            interval: Interval { start, end },
+        };
+        let config = UptimeConfig {
+            assignments: vec![assign(1, 0, 50), assign(2, 50, 80), assign(1, 80, u64::MAX)],
+            ..UptimeConfig::default()
+        };
+        let reports = vec![
+            ChargerReport {
+                charger: ChargerId(100),
+                connector: None,
+                interval: Interval { start: 0, end: 40 },
+                status: ChargerStatus::Down,
+            },
+            ChargerReport {
+                charger: ChargerId(200),
+                connector: None,
+                interval: Interval { start: 0, end: 100 },
+                status: ChargerStatus::Up,
+            },
+        ];
+        let res = compute_station_report(&stations, &reports, &config).unwrap();
+        // Station 1 only counts C200's two stints; the time away is not downtime
+        assert_eq!(res[0].reported, 70);
+        assert_eq!(res[0].percent, 100);
+        assert_eq!(res[1].reported, 30);
+        assert_eq!(res[1].percent, 100);
+    }
+
+    #[test]
+    fn connectors_roll_up_with_any_or_all() {
+        // Charger 10 has two connectors; connector 2 is down from 50 to 100.
+        // Charger 11 is up throughout.
+        let stations = vec![Station {
+            id: StationId(1),
+            chargers: vec![ChargerId(10), ChargerId(11)],
+        }];
+        let report =
+            |charger: u32, connector: Option<u32>, start: u64, end: u64, up: bool| ChargerReport {
+                charger: ChargerId(charger),
+                connector: connector.map(ConnectorId),
+                interval: Interval { start, end },
+                status: up.into(),
+            };
+        let reports = vec![
+            report(10, Some(1), 0, 100, true),
+            report(10, Some(2), 0, 50, true),
+            report(10, Some(2), 50, 100, false),
+            report(11, None, 0, 100, true),
+        ];
+        let percent = |charger_rollup: Rollup, connector_rollup: Rollup| {
+            let config = UptimeConfig {
+                charger_rollup,
+                connector_rollup,
+                ..UptimeConfig::default()
+            };
+            compute_station_report(&stations, &reports, &config).unwrap()[0].percent
+        };
+        assert_eq!(percent(Rollup::Any, Rollup::Any), 100);
+        assert_eq!(percent(Rollup::All, Rollup::Any), 100);
+        assert_eq!(percent(Rollup::Any, Rollup::All), 100);
+        assert_eq!(percent(Rollup::All, Rollup::All), 50);
+
+        let config = UptimeConfig {
+            connector_rollup: Rollup::All,
+            ..UptimeConfig::default()
+        };
+        let percents = |level| -> Vec<(u32, Option<u32>, u8)> {
+            compute_unit_results(&stations, &reports, &config, level)
+                .into_iter()
+                .map(|u| match u.result {
+                    StationResult::Computed(r) => {
+                        (u.charger.0, u.connector.map(|c| c.0), r.percent)
+                    }
+                    StationResult::NoData(_) => panic!("every charger reported"),
+                })
+                .collect()
+        };
+        assert_eq!(
+            percents(UnitLevel::Charger),
+            vec![(10, None, 50), (11, None, 100)]
+        );
+        assert_eq!(
+            percents(UnitLevel::Connector),
+            vec![(10, Some(1), 100), (10, Some(2), 50), (11, None, 100)]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn results_serialize_with_plain_ids() {
+        let mut by_status = BTreeMap::new();
+        by_status.insert(ChargerStatus::Up, 10);
+        let results = vec![
+            StationResult::Computed(StationUptime {
+                station: StationId(1),
+                percent: 100,
+                raw_percent: 100,
+                available: 10,
+                reported: 10,
+                excluded: 0,
+                excused: 0,
+                by_status,
+            }),
+            StationResult::NoData(StationId(2)),
+        ];
+        let json = serde_json::to_string(&results).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"computed":{"station":1,"percent":100,"raw_percent":100,"available":10,"reported":10,"excluded":0,"excused":0,"by_status":{"up":10}}},{"no_data":2}]"#
+        );
+        let back: Vec<StationResult> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, results);
+    }
 }
@@ -0,0 +1,306 @@
+use crate::csv_reader::CsvOptions;
+use crate::parser::ParsedInput;
+use crate::types::{ChargerReport, ChargerStatus, Station, WindowTarget};
+use std::fmt::Write;
+
+/// Serialize stations and reports into the challenge format; the inverse of
+/// `parse_input`, so parsing the result yields the same data.
+pub fn write_input(stations: &[Station], reports: &[ChargerReport]) -> String {
+    write_document(&ParsedInput {
+        stations: stations.to_vec(),
+        reports: reports.to_vec(),
+        ..ParsedInput::default()
+    })
+}
+
+/// Serialize a parsed document back into the bracketed text format accepted
+/// by `parse_document`. Directives and optional sections are only written when
+/// present, and the reports section is left out of a stations-only document
+/// so it reads back with `parse_topology`. Comments are not preserved.
+pub fn write_document(doc: &ParsedInput) -> String {
+    let mut out = String::new();
+
+    let header = &doc.header;
+    let directives = [
+        ("source", header.source.clone()),
+        ("exported_at", header.exported_at.clone()),
+        ("time_unit", header.time_unit.map(|u| u.to_string())),
+        (
+            "format_version",
+            header.format_version.map(|v| v.to_string()),
+        ),
+    ];
+    for (key, value) in directives {
+        if let Some(value) = value {
+            let _ = writeln!(out, "@{key} {value}");
+        }
+    }
+    for (key, value) in &header.extra {
+        let _ = writeln!(out, "@{key} {value}");
+    }
+
+    out.push_str("[Stations]\n");
+    for station in &doc.stations {
+        let _ = write!(out, "{}", station.id.0);
+        for charger in &station.chargers {
+            let _ = write!(out, " {}", charger.0);
+        }
+        out.push('\n');
+    }
+
+    if !doc.metadata.is_empty() {
+        out.push_str("\n[Station Metadata]\n");
+        for (station, metadata) in &doc.metadata {
+            for (key, value) in metadata.entries() {
+                let _ = writeln!(out, "{} {key} {value}", station.0);
+            }
+        }
+    }
+
+    if !doc.assignments.is_empty() {
+        out.push_str("\n[Charger Assignments]\n");
+        for a in &doc.assignments {
+            let _ = write!(out, "{} {} {}", a.charger.0, a.station.0, a.interval.start);
+            if a.interval.end != u64::MAX {
+                let _ = write!(out, " {}", a.interval.end);
+            }
+            out.push('\n');
+        }
+    }
+
+    if !doc.reports.is_empty() {
+        out.push_str("\n[Charger Availability Reports]\n");
+        for r in &doc.reports {
+            let _ = writeln!(
+                out,
+                "{} {} {} {}",
+                r.token(),
+                r.interval.start,
+                r.interval.end,
+                status_token(r.status)
+            );
+        }
+    }
+
+    if !doc.maintenance.is_empty() {
+        out.push_str("\n[Maintenance Windows]\n");
+        for w in &doc.maintenance {
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                target_tokens(w.target),
+                w.interval.start,
+                w.interval.end
+            );
+        }
+    }
+
+    if !doc.outages.is_empty() {
+        out.push_str("\n[Outages]\n");
+        for o in &doc.outages {
+            let _ = writeln!(
+                out,
+                "{} {} {} {}",
+                target_tokens(o.target),
+                o.interval.start,
+                o.interval.end,
+                o.category
+            );
+        }
+    }
+
+    out
+}
+
+/// Write reports as CSV with a header row, using the columns and delimiter
+/// that `parse_csv_reports_with` reads with the same `csv` options. The
+/// connector column is only written when some report names a connector.
+pub fn write_csv_reports(reports: &[ChargerReport], csv: &CsvOptions) -> String {
+    let with_connector = reports.iter().any(|r| r.connector.is_some());
+    let mut writer = csv_writer(csv);
+    let mut header = vec![&csv.charger_column];
+    if with_connector {
+        header.push(&csv.connector_column);
+    }
+    header.extend([&csv.start_column, &csv.end_column, &csv.status_column]);
+    writer.write_record(header).expect(CSV_IN_MEMORY);
+    for r in reports {
+        let mut record = vec![r.charger.0.to_string()];
+        if with_connector {
+            record.push(r.connector.map_or(String::new(), |c| c.0.to_string()));
+        }
+        record.extend([
+            r.interval.start.to_string(),
+            r.interval.end.to_string(),
+            status_token(r.status).to_string(),
+        ]);
+        writer.write_record(record).expect(CSV_IN_MEMORY);
+    }
+    csv_string(writer)
+}
+
+/// Write one `station, charger` row per charger, as read by `parse_csv_stations`.
+pub fn write_csv_stations(stations: &[Station], csv: &CsvOptions) -> String {
+    let mut writer = csv_writer(csv);
+    writer
+        .write_record([&csv.station_column, &csv.charger_column])
+        .expect(CSV_IN_MEMORY);
+    for station in stations {
+        for charger in &station.chargers {
+            writer
+                .write_record([station.id.0.to_string(), charger.0.to_string()])
+                .expect(CSV_IN_MEMORY);
+        }
+    }
+    csv_string(writer)
+}
+
+const CSV_IN_MEMORY: &str = "writing CSV to memory cannot fail";
+
+fn csv_writer(csv: &CsvOptions) -> csv::Writer<Vec<u8>> {
+    csv::WriterBuilder::new()
+        .delimiter(csv.delimiter)
+        .from_writer(Vec::new())
+}
+
+fn csv_string(writer: csv::Writer<Vec<u8>>) -> String {
+    let bytes = writer.into_inner().expect(CSV_IN_MEMORY);
+    String::from_utf8(bytes).expect("CSV fields are ASCII")
+}
+
+/// Write the stations and reports as the JSON document read by
+/// `parse_json_document_with`.
+#[cfg(feature = "serde")]
+pub fn write_json_document(stations: &[Station], reports: &[ChargerReport]) -> String {
+    let document = serde_json::json!({ "stations": stations, "reports": reports });
+    let mut out = serde_json::to_string_pretty(&document).expect("plain data serializes");
+    out.push('\n');
+    out
+}
+
+/// Write one JSON report object per line, as read by `parse_ndjson_reports_with`.
+#[cfg(feature = "serde")]
+pub fn write_ndjson_reports(reports: &[ChargerReport]) -> String {
+    let mut out = String::new();
+    for r in reports {
+        out.push_str(&serde_json::to_string(r).expect("plain data serializes"));
+        out.push('\n');
+    }
+    out
+}
+
+// Legacy consumers only understand true/false, so keep those for up/down.
+fn status_token(status: ChargerStatus) -> &'static str {
+    match status {
+        ChargerStatus::Up => "true",
+        ChargerStatus::Down => "false",
+        other => other.as_str(),
+    }
+}
+
+fn target_tokens(target: WindowTarget) -> String {
+    match target {
+        WindowTarget::Station(id) => format!("station {}", id.0),
+        WindowTarget::Charger(id) => format!("charger {}", id.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+    use crate::types::StationId;
+
+    #[test]
+    fn writes_challenge_format() {
+        let input = "[Stations]\n1 100 101\n\n[Charger Availability Reports]\n100 0 10 true\n101 0 10 faulted\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(write_document(&doc), input);
+    }
+
+    #[test]
+    fn writes_metadata_after_stations() {
+        let input = "[Stations]\n1 100\n\n[Station Metadata]\n1 name Main Street Depot\n1 operator Acme\n1 parking covered\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(doc.metadata[&StationId(1)].tags["parking"], "covered");
+        assert_eq!(write_document(&doc), input);
+    }
+
+    #[test]
+    fn writes_assignments_after_stations() {
+        let input = "[Stations]\n1 100\n2\n\n[Charger Assignments]\n200 1 0 50\n200 2 50\n\n[Charger Availability Reports]\n200 0 100 true\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(write_document(&doc), input);
+    }
+
+    #[test]
+    fn parse_write_parse_round_trips() {
+        let input = "[Stations]\n2 200 201\n1 100\n[Charger Availability Reports]\n201 5 9 idle\n100 0 10 true\n200 0 10 false\n100 10 20 maintenance\n";
+        let (stations, reports) = crate::parser::parse_input(input).unwrap();
+        let written = write_input(&stations, &reports);
+        assert_eq!(
+            crate::parser::parse_input(&written).unwrap(),
+            (stations, reports)
+        );
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        use crate::csv_reader::{parse_csv_reports_with, parse_csv_stations};
+        let doc = parse_document("[Stations]\n1 100 101\n[Charger Availability Reports]\n100 0 10 true\n101 0 10 faulted\n").unwrap();
+        let csv = CsvOptions {
+            delimiter: b';',
+            ..CsvOptions::default()
+        };
+        let stations = write_csv_stations(&doc.stations, &csv);
+        assert_eq!(stations, "station_id;charger_id\n1;100\n1;101\n");
+        let topology = parse_csv_stations(&stations, &csv).unwrap();
+        assert_eq!(topology.stations, doc.stations);
+        let reports = write_csv_reports(&doc.reports, &csv);
+        let back = parse_csv_reports_with(&reports, &csv, &topology, &Default::default()).unwrap();
+        assert_eq!(back.reports, doc.reports);
+    }
+
+    #[test]
+    fn connectors_round_trip() {
+        use crate::csv_reader::parse_csv_reports_with;
+        let input = "[Stations]\n1 100\n\n[Charger Availability Reports]\n100:1 0 10 true\n100 0 10 false\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(write_document(&doc), input);
+        let csv = write_csv_reports(&doc.reports, &CsvOptions::default());
+        assert_eq!(
+            csv,
+            "charger_id,connector_id,start,end,up\n100,1,0,10,true\n100,,0,10,false\n"
+        );
+        let back = parse_csv_reports_with(&csv, &CsvOptions::default(), &doc, &Default::default())
+            .unwrap();
+        assert_eq!(back.reports, doc.reports);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips() {
+        use crate::json_reader::{parse_json_document_with, parse_ndjson_reports_with};
+        let doc = parse_document("[Stations]\n1 100 101\n[Charger Availability Reports]\n100 0 10 true\n101 0 10 faulted\n").unwrap();
+        let json = write_json_document(&doc.stations, &doc.reports);
+        let back = parse_json_document_with(&json, &Default::default()).unwrap();
+        assert_eq!(
+            (back.stations, back.reports),
+            (doc.stations.clone(), doc.reports.clone())
+        );
+        let ndjson = write_ndjson_reports(&doc.reports);
+        assert_eq!(
+            ndjson.lines().next(),
+            Some(r#"{"charger":100,"start":0,"end":10,"status":"up"}"#)
+        );
+        let back = parse_ndjson_reports_with(&ndjson, &doc, &Default::default()).unwrap();
+        assert_eq!(back.reports, doc.reports);
+    }
+
+    #[test]
+    fn writes_directives_first() {
+        let input = "@source test\n@time_unit s\n@batch 7\n[Stations]\n1 100\n\n[Charger Availability Reports]\n100 0 10 true\n";
+        let doc = parse_document(input).unwrap();
+        assert_eq!(write_document(&doc), input);
+    }
+}
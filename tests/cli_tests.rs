@@ -1,4 +1,5 @@
 use assert_cmd::prelude::*;
+use predicates::prelude::*;
 use std::fs;
 use std::process::Command;
 
@@ -22,3 +23,567 @@ fn cli_runs_on_fixture_inputs() {
     cmd2.arg("fixtures/input_2.txt");
     cmd2.assert().success().stdout(expected2);
 }
+
+#[test]
+fn cli_exit_codes_for_errors_and_sla_breaches() {
+    // input_1 has station 1 at 0% uptime
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--sla", "97", "fixtures/input_1.txt"]);
+    cmd.assert()
+        .code(3)
+        .stderr(predicates::str::contains("station 1"));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--sla", "0", "fixtures/input_1.txt"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("fixtures/does_not_exist.txt");
+    cmd.assert().code(1).stdout("");
+
+    // Compatibility mode keeps the challenge contract
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--compat", "fixtures/does_not_exist.txt"]);
+    cmd.assert().success().stdout("ERROR\n");
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--compat", "--sla", "97", "fixtures/input_1.txt"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--compat",
+        "validate",
+        "fixtures/input_1_expected_stdout.txt",
+    ]);
+    cmd.assert().success().stdout("ERROR\n");
+}
+
+#[test]
+fn cli_subcommands() {
+    let expected = fs::read_to_string("fixtures/input_1_expected_stdout.txt").unwrap();
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["compute", "fixtures/input_1.txt"]);
+    cmd.assert()
+        .success()
+        .stdout(format!("{}\n", expected.trim_end()));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["validate", "fixtures/input_1.txt"]);
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::starts_with("valid:"));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "validate",
+        "--format",
+        "json",
+        "fixtures/input_1_expected_stdout.txt",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stdout(predicates::str::contains("\"valid\": false"));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["outages", "fixtures/input_1.txt"]);
+    cmd.assert()
+        .success()
+        .stdout("1 25000 75000\n2 50000 100000\n");
+
+    // convert re-emits input the parser accepts and that yields the same uptimes
+    let out = Command::cargo_bin("charger-uptime")
+        .unwrap()
+        .args(["convert", "fixtures/input_2.txt"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let dir = tempfile::tempdir().unwrap();
+    let converted = dir.path().join("input_2.txt");
+    fs::write(&converted, out.stdout).unwrap();
+    let expected = fs::read_to_string("fixtures/input_2_expected_stdout.txt").unwrap();
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg(&converted);
+    cmd.assert()
+        .success()
+        .stdout(format!("{}\n", expected.trim_end()));
+}
+
+#[test]
+fn cli_validate_reports_data_quality() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["validate", "--now", "1000", "fixtures/quality_issues.txt"]);
+    cmd.assert().success().stdout(
+        "valid: 2 stations, 3 reports\n\
+         charger without reports: 101\n\
+         charger without reports: 200\n\
+         station without reports: 2\n\
+         conflicting reports: charger 100 [0, 10) up and [5, 20) down\n\
+         duplicate report: charger 100 [0, 10) up x2\n",
+    );
+
+    // The same file aborts compute because station 2 never reported
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("fixtures/quality_issues.txt");
+    cmd.assert().code(1);
+}
+
+#[test]
+fn cli_allow_missing_keeps_other_stations() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--allow-missing", "fixtures/quality_issues.txt"]);
+    cmd.assert().success().stdout("1 50\n2 N/A\n");
+
+    // A station without reports is not an SLA breach, only station 1 is
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--allow-missing",
+        "--sla",
+        "90",
+        "fixtures/quality_issues.txt",
+    ]);
+    cmd.assert()
+        .code(3)
+        .stdout("1 50\n2 N/A\n")
+        .stderr(predicates::str::contains("station 1"))
+        .stderr(predicates::str::contains("station 2").not());
+}
+
+#[test]
+fn cli_lenient_skips_malformed_reports() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("fixtures/malformed_reports.txt");
+    cmd.assert().code(1);
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--lenient", "fixtures/malformed_reports.txt"]);
+    cmd.assert()
+        .success()
+        .stdout("1 50\n")
+        .stderr(predicates::str::contains(
+            "skipped 1 invalid report line(s)",
+        ));
+}
+
+#[test]
+fn cli_merges_multiple_inputs() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("fixtures/daily/day_*.txt");
+    cmd.assert().success().stdout("1 50\n2 100\n");
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["fixtures/daily/day_1.txt", "fixtures/daily_conflict.txt"]);
+    cmd.assert().code(1).stderr(predicates::str::contains(
+        "station 1 has different chargers at fixtures/daily/day_1.txt:2 and fixtures/daily_conflict.txt:2",
+    ));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("fixtures/daily/none_*.txt");
+    cmd.assert()
+        .code(1)
+        .stderr(predicates::str::contains("no input files match"));
+}
+
+#[test]
+fn cli_reads_topology_separately() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--stations",
+        "fixtures/split/topology.txt",
+        "fixtures/split/reports_*.txt",
+    ]);
+    cmd.assert().success().stdout("1 50\n2 100\n");
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--stations",
+        "fixtures/split/topology.txt",
+        "fixtures/split/unknown_charger.txt",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicates::str::contains("unknown charger id 300 (line 3)"));
+
+    // Without the topology the report files are incomplete
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("fixtures/split/reports_1.txt");
+    cmd.assert().code(1);
+}
+
+#[test]
+fn cli_reads_csv_reports() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "csv",
+        "--stations",
+        "fixtures/csv/stations.csv",
+        "fixtures/csv/reports.csv",
+    ]);
+    cmd.assert().success().stdout("1 50\n2 100\n");
+
+    // Stations from the bracketed format, custom columns and delimiter
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "csv",
+        "--stations",
+        "fixtures/csv/topology.txt",
+        "--csv-delimiter",
+        ";",
+        "--csv-charger-column",
+        "evse",
+        "--csv-start-column",
+        "from",
+        "--csv-end-column",
+        "to",
+        "--csv-status-column",
+        "state",
+        "fixtures/csv/reports_custom.csv",
+    ]);
+    cmd.assert().success().stdout("1 50\n2 100\n");
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--input-format", "csv", "fixtures/csv/reports.csv"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicates::str::contains("needs a --stations file"));
+}
+
+#[test]
+fn cli_reads_json_inputs() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--input-format", "json", "fixtures/json/document.json"]);
+    cmd.assert().success().stdout("1 50\n2 100\n");
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "ndjson",
+        "--stations",
+        "fixtures/csv/topology.txt",
+        "fixtures/json/reports.ndjson",
+    ]);
+    cmd.assert().success().stdout("1 50\n2 100\n");
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "ndjson",
+        "--stations",
+        "fixtures/csv/topology.txt",
+        "fixtures/json/bad_record.ndjson",
+    ]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicates::str::contains("report record 2:"));
+}
+
+#[test]
+fn cli_converts_between_formats() {
+    let expected = fs::read_to_string("fixtures/input_2_expected_stdout.txt").unwrap();
+    let expected = format!("{}\n", expected.trim_end());
+    let dir = tempfile::tempdir().unwrap();
+
+    let json = dir.path().join("input_2.json");
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["convert", "--to", "json", "fixtures/input_2.txt", "-o"])
+        .arg(&json);
+    cmd.assert().success();
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--input-format", "json"]).arg(&json);
+    cmd.assert().success().stdout(expected.clone());
+
+    let reports = dir.path().join("reports.csv");
+    let stations = dir.path().join("stations.csv");
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["convert", "--to", "csv", "fixtures/input_2.txt", "-o"])
+        .arg(&reports)
+        .arg("--stations-output")
+        .arg(&stations);
+    cmd.assert().success();
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--input-format", "csv", "--stations"])
+        .arg(&stations)
+        .arg(&reports);
+    cmd.assert().success().stdout(expected);
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["convert", "--to", "ndjson", "fixtures/input_2.txt"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicates::str::contains("--stations-output"));
+}
+
+#[test]
+fn cli_reads_compressed_inputs() {
+    let expected = fs::read_to_string("fixtures/input_1_expected_stdout.txt").unwrap();
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("fixtures/compressed/input_1.txt.gz");
+    cmd.assert()
+        .success()
+        .stdout(format!("{}\n", expected.trim_end()));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "ndjson",
+        "--stations",
+        "fixtures/csv/topology.txt",
+        "fixtures/compressed/reports.ndjson.zst",
+    ]);
+    cmd.assert().success().stdout("1 50\n2 100\n");
+}
+
+#[test]
+fn cli_imports_ocpp_status_notifications() {
+    // Station 1 is available for 50 of 100 seconds
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "ocpp",
+        "--stations",
+        "fixtures/split/topology.txt",
+        "--ocpp-until",
+        "100000000000",
+        "fixtures/ocpp/status_notifications.ndjson",
+    ]);
+    cmd.assert().success().stdout("1 50\n2 100\n");
+}
+
+#[test]
+fn cli_infers_availability_from_heartbeats() {
+    // Charger 100 misses the heartbeats at 50, 60 and 70: down from 50 to 80
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "heartbeat",
+        "--stations",
+        "fixtures/split/topology.txt",
+        "--heartbeat-period",
+        "10",
+        "fixtures/heartbeat/heartbeats.txt",
+    ]);
+    cmd.assert().success().stdout("1 70\n2 100\n");
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--input-format",
+        "heartbeat",
+        "--stations",
+        "fixtures/split/topology.txt",
+        "fixtures/heartbeat/heartbeats.txt",
+    ]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicates::str::contains("needs --heartbeat-period"));
+}
+
+#[test]
+fn cli_computes_connector_and_charger_levels() {
+    let run = |args: &[&str], expected: &str| {
+        let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+        cmd.args(args).arg("fixtures/connectors.txt");
+        cmd.assert().success().stdout(expected.to_string());
+    };
+    run(&[], "1 100\n2 0\n");
+    run(
+        &["--connector-rollup", "all", "--charger-rollup", "all"],
+        "1 50\n2 0\n",
+    );
+    run(
+        &["--level", "connector"],
+        "1 100:1 100\n1 100:2 50\n1 101 100\n2 200 0\n",
+    );
+    run(
+        &["--level", "charger", "--connector-rollup", "all"],
+        "1 100 50\n1 101 100\n2 200 0\n",
+    );
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--level",
+        "charger",
+        "--sla",
+        "90",
+        "fixtures/connectors.txt",
+    ]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicates::str::contains("only apply at --level station"));
+}
+
+#[test]
+fn cli_carries_station_metadata_to_outputs() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--format",
+        "csv",
+        "--metadata",
+        "fixtures/metadata/stations.csv",
+        "fixtures/metadata/input.txt",
+    ]);
+    cmd.assert().success().stdout(
+        "station,uptime,raw_uptime,available,reported,excluded,excused,name,region,operator,network,parking\n\
+         1,100,100,100,100,0,0,Main Street,north,Acme,ChargeNet,covered\n\
+         2,50,50,50,100,0,0,\"Depot, Yard 2\",south,,ChargeNet,\n",
+    );
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--format",
+        "json",
+        "--metadata",
+        "fixtures/metadata/stations.csv",
+        "fixtures/metadata/input.txt",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["stations"][0]["metadata"]["name"], "Main Street");
+    assert_eq!(
+        json["stations"][0]["metadata"]["tags"]["parking"],
+        "covered"
+    );
+    assert_eq!(json["stations"][1]["metadata"]["network"], "ChargeNet");
+    // Rows carry the `StationUptime` fields as the library serializes them
+    let station = &json["stations"][0];
+    assert!(station["percent"].is_u64() && station["by_status"].is_object());
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--metadata",
+        "fixtures/metadata/unknown_station.csv",
+        "fixtures/metadata/input.txt",
+    ]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicates::str::contains(
+            "metadata references unknown station id",
+        ));
+}
+
+#[test]
+fn cli_groups_stations_by_metadata() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--group-by", "region", "fixtures/metadata/fleet.txt"]);
+    cmd.assert().success().stdout(
+        "region=- stations=1 no_data=0 uptime=100.00 min=100 p5=100 p50=100 p95=100 max=100\n\
+         region=north stations=2 no_data=0 uptime=80.00 min=75 p5=75 p50=75 p95=100 max=100\n\
+         region=south stations=1 no_data=0 uptime=50.00 min=50 p5=50 p50=50 p95=50 max=50\n",
+    );
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "--group-by",
+        "model",
+        "--format",
+        "json",
+        "fixtures/metadata/fleet.txt",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let x = &json["groups"][1];
+    assert_eq!(x["key"]["model"], "X");
+    assert_eq!(
+        (x["available"].as_u64(), x["reported"].as_u64()),
+        (Some(150), Some(200))
+    );
+    assert_eq!(x["uptime"], 75.0);
+
+    // Group sums beyond u64::MAX
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("long_reports.txt");
+    fs::write(
+        &input,
+        "[Stations]\n1 100\n2 200\n\n\
+         [Station Metadata]\n1 region north\n2 region north\n\n\
+         [Charger Availability Reports]\n\
+         100 0 10000000000000000000 true\n\
+         200 0 10000000000000000000 false\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--group-by", "region", "--format", "json"])
+        .arg(&input);
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "\"available\": 10000000000000000000,",
+        ))
+        .stdout(predicates::str::contains(
+            "\"reported\": 20000000000000000000,",
+        ))
+        .stdout(predicates::str::contains("\"uptime\": 50.0,"));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["--group-by", "regoin", "fixtures/metadata/fleet.txt"]);
+    cmd.assert().code(1).stderr(predicates::str::contains(
+        "no station has metadata key 'regoin'",
+    ));
+}
+
+#[test]
+fn cli_stats_summarizes_fleet_uptime() {
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["stats", "--worst", "2", "fixtures/metadata/fleet.txt"]);
+    cmd.assert().success().stdout(predicates::str::ends_with(
+        "stations_without_data 0\n\
+         fleet_uptime 78.57\n\
+         p5 50\n\
+         p50 75\n\
+         p95 100\n\
+         below 97 2\n\
+         worst 3 50.00\n\
+         worst 2 75.00\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args([
+        "stats",
+        "--format",
+        "json",
+        "--below",
+        "80",
+        "fixtures/metadata/fleet.txt",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let fleet = &json["fleet"];
+    assert_eq!(json["stations"], 4);
+    assert_eq!(
+        (fleet["target"].as_f64(), fleet["below_target"].as_u64()),
+        (Some(80.0), Some(2))
+    );
+    assert_eq!(fleet["percentiles"]["p50"], 75);
+    assert_eq!(fleet["worst"].as_array().unwrap().len(), 4);
+    assert_eq!(fleet["worst"][0]["station"], 3);
+
+    // Total reported time beyond u64::MAX
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("long_reports.txt");
+    fs::write(
+        &input,
+        "[Stations]\n1 100\n2 200\n\n\
+         [Charger Availability Reports]\n\
+         100 0 10000000000000000000 true\n\
+         200 0 10000000000000000000 true\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.arg("stats").arg(&input);
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("fleet_uptime 100.00\n"));
+
+    let mut cmd = Command::cargo_bin("charger-uptime").unwrap();
+    cmd.args(["stats", "--format", "json"]).arg(&input);
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "\"reported\": 20000000000000000000,",
+        ))
+        .stdout(predicates::str::contains("\"uptime\": 100.0,"));
+}